@@ -27,6 +27,30 @@ void nak_compiler_destroy(struct nak_compiler *nak);
 
 uint64_t nak_debug_flags(const struct nak_compiler *nak);
 
+/* Counters aggregated over every shader compiled with a given nak_compiler */
+struct nak_compiler_stats {
+   uint64_t num_compiles;
+   uint64_t num_failed_compiles;
+
+   /** Total time spent in nak_compile_shader(), in nanoseconds */
+   uint64_t compile_time_ns;
+
+   uint64_t num_spills_to_mem;
+   uint64_t num_spills_to_reg;
+
+   /** Shaders the driver loaded from a cache instead of compiling */
+   uint64_t num_cache_hits;
+
+   /** Shaders the driver compiled with codegen instead of NAK */
+   uint64_t num_codegen_fallbacks;
+};
+
+void nak_compiler_record_cache_hit(const struct nak_compiler *nak);
+void nak_compiler_record_codegen_fallback(const struct nak_compiler *nak);
+
+void nak_get_compiler_stats(const struct nak_compiler *nak,
+                            struct nak_compiler_stats *stats);
+
 const struct nir_shader_compiler_options *
 nak_nir_options(const struct nak_compiler *nak);
 
@@ -83,7 +107,7 @@ static_assert(sizeof(struct nak_fs_key) == 12, "This struct has no holes");
 
 
 void nak_postprocess_nir(nir_shader *nir, const struct nak_compiler *nak,
-                         nir_variable_mode robust2_modes,
+                         nir_variable_mode robust2_modes, bool fast_math,
                          const struct nak_fs_key *fs_key);
 
 enum ENUM_PACKED nak_ts_domain {
@@ -143,6 +167,27 @@ struct nak_shader_info {
    /** Number of instructions used */
    uint32_t num_instrs;
 
+   /** Number of instructions issued to the FMA pipe */
+   uint32_t num_fma_instrs;
+
+   /** Number of instructions issued to the integer/logic ALU pipe */
+   uint32_t num_alu_instrs;
+
+   /**
+    * Number of multi-function, double-precision, bit-counting, and conversion
+    * instructions
+    */
+   uint32_t num_xu_instrs;
+
+   /** Number of memory, texture, and attribute instructions */
+   uint32_t num_mem_instrs;
+
+   /**
+    * Number of control-flow, barrier, and system value instructions, not
+    * counting NOPs
+    */
+   uint32_t num_ctrl_instrs;
+
    /** Number of cycles used by fixed-latency instructions */
    uint32_t num_static_cycles;
 
@@ -225,11 +270,33 @@ struct nak_shader_bin {
 
 void nak_shader_bin_destroy(struct nak_shader_bin *bin);
 
+struct nak_compile_params {
+   /** Dump the final assembly into nak_shader_bin::asm_str */
+   bool dump_asm;
+
+   /** Allow floating-point optimizations which ignore signed zero, Inf, and
+    * NaN preservation requirements.  Rounding and denorm modes are still
+    * honored.
+    */
+   bool fast_math;
+
+   /** 0 disables the optional NAK IR optimization passes.  Any other value
+    * (NAK_OPT_LEVEL_DEFAULT is 1) runs all of them.
+    */
+   uint8_t opt_level;
+
+   /** Variable modes which require robustBufferAccess2 semantics */
+   nir_variable_mode robust2_modes;
+
+   /** Fragment shader key or NULL */
+   const struct nak_fs_key *fs_key;
+};
+
+#define NAK_OPT_LEVEL_DEFAULT 1
+
 struct nak_shader_bin *
-nak_compile_shader(nir_shader *nir, bool dump_asm,
-                   const struct nak_compiler *nak,
-                   nir_variable_mode robust2_modes,
-                   const struct nak_fs_key *fs_key);
+nak_compile_shader(nir_shader *nir, const struct nak_compiler *nak,
+                   const struct nak_compile_params *params);
 
 struct nak_qmd_cbuf {
    uint32_t index;
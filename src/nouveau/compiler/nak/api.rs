@@ -2,7 +2,11 @@
 // SPDX-License-Identifier: MIT
 
 use crate::from_nir::*;
-use crate::ir::{ShaderInfo, ShaderIoInfo, ShaderModel, ShaderStageInfo};
+use crate::ir::{
+    RegFile, Shader, ShaderInfo, ShaderIoInfo, ShaderModel, ShaderStageInfo,
+};
+use crate::ir_diff::ShaderSnapshot;
+use crate::liveness::{Liveness, SimpleLiveness};
 use crate::sm50::ShaderModel50;
 use crate::sm70::ShaderModel70;
 use crate::sph;
@@ -13,10 +17,13 @@ use nak_bindings::*;
 use std::cmp::max;
 use std::env;
 use std::ffi::{CStr, CString};
+use std::fmt;
 use std::fmt::Write;
 use std::os::raw::c_void;
 use std::panic;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::OnceLock;
+use std::time::Instant;
 
 #[repr(u8)]
 enum DebugFlags {
@@ -25,19 +32,45 @@ enum DebugFlags {
     Spill,
     Annotate,
     NoUgpr,
+    Conservative,
+    Diff,
+    LiveRanges,
+    Ilp,
 }
 
 pub struct Debug {
     flags: u32,
+    max_gprs: Option<u32>,
+    unknown_sm_margin: Option<u32>,
+}
+
+fn env_u32(var: &str) -> Option<u32> {
+    match env::var(var) {
+        Ok(s) => match s.trim().parse::<u32>() {
+            Ok(n) => Some(n),
+            Err(_) => {
+                eprintln!("Invalid {} value \"{}\"", var, s);
+                None
+            }
+        },
+        Err(_) => None,
+    }
 }
 
 impl Debug {
     fn new() -> Debug {
+        let max_gprs = env_u32("NAK_MAX_GPRS");
+        let unknown_sm_margin = env_u32("NAK_UNKNOWN_SM_MARGIN");
+
         let debug_var = "NAK_DEBUG";
         let debug_str = match env::var(debug_var) {
             Ok(s) => s,
             Err(_) => {
-                return Debug { flags: 0 };
+                return Debug {
+                    flags: 0,
+                    max_gprs: max_gprs,
+                    unknown_sm_margin: unknown_sm_margin,
+                };
             }
         };
 
@@ -49,16 +82,31 @@ impl Debug {
                 "spill" => flags |= 1 << DebugFlags::Spill as u8,
                 "annotate" => flags |= 1 << DebugFlags::Annotate as u8,
                 "nougpr" => flags |= 1 << DebugFlags::NoUgpr as u8,
+                "conservative" => flags |= 1 << DebugFlags::Conservative as u8,
+                "diff" => flags |= 1 << DebugFlags::Diff as u8,
+                "liveranges" => flags |= 1 << DebugFlags::LiveRanges as u8,
+                "ilp" => flags |= 1 << DebugFlags::Ilp as u8,
                 unk => eprintln!("Unknown NAK_DEBUG flag \"{}\"", unk),
             }
         }
-        Debug { flags: flags }
+        Debug {
+            flags: flags,
+            max_gprs: max_gprs,
+            unknown_sm_margin: unknown_sm_margin,
+        }
     }
 }
 
 pub trait GetDebugFlags {
     fn debug_flags(&self) -> u32;
 
+    /// The default for NakCompileParams::max_gprs, set with NAK_MAX_GPRS
+    fn max_gprs(&self) -> Option<u32>;
+
+    /// Overrides the latency margin used on SMs newer than any we have
+    /// measured, set with NAK_UNKNOWN_SM_MARGIN
+    fn unknown_sm_margin(&self) -> Option<u32>;
+
     fn print(&self) -> bool {
         self.debug_flags() & (1 << DebugFlags::Print as u8) != 0
     }
@@ -78,6 +126,22 @@ pub trait GetDebugFlags {
     fn no_ugpr(&self) -> bool {
         self.debug_flags() & (1 << DebugFlags::NoUgpr as u8) != 0
     }
+
+    fn conservative(&self) -> bool {
+        self.debug_flags() & (1 << DebugFlags::Conservative as u8) != 0
+    }
+
+    fn diff(&self) -> bool {
+        self.debug_flags() & (1 << DebugFlags::Diff as u8) != 0
+    }
+
+    fn live_ranges(&self) -> bool {
+        self.debug_flags() & (1 << DebugFlags::LiveRanges as u8) != 0
+    }
+
+    fn ilp(&self) -> bool {
+        self.debug_flags() & (1 << DebugFlags::Ilp as u8) != 0
+    }
 }
 
 pub static DEBUG: OnceLock<Debug> = OnceLock::new();
@@ -86,6 +150,14 @@ impl GetDebugFlags for OnceLock<Debug> {
     fn debug_flags(&self) -> u32 {
         self.get_or_init(Debug::new).flags
     }
+
+    fn max_gprs(&self) -> Option<u32> {
+        self.get_or_init(Debug::new).max_gprs
+    }
+
+    fn unknown_sm_margin(&self) -> Option<u32> {
+        self.get_or_init(Debug::new).unknown_sm_margin
+    }
 }
 
 #[no_mangle]
@@ -109,8 +181,9 @@ fn nir_options(dev: &nv_device_info) -> nir_shader_compiler_options {
     op.lower_pack_half_2x16 = true;
     op.lower_pack_unorm_2x16 = true;
     op.lower_pack_snorm_2x16 = true;
-    op.lower_pack_unorm_4x8 = true;
-    op.lower_pack_snorm_4x8 = true;
+    // Volta+ has 8-bit F2I so from_nir handles 4x8 packs directly
+    op.lower_pack_unorm_4x8 = dev.sm < 70;
+    op.lower_pack_snorm_4x8 = dev.sm < 70;
     op.lower_unpack_half_2x16 = true;
     op.lower_unpack_unorm_2x16 = true;
     op.lower_unpack_snorm_2x16 = true;
@@ -159,7 +232,11 @@ fn nir_options(dev: &nv_device_info) -> nir_shader_compiler_options {
     op.has_fmulz_no_denorms = true;
     op.has_find_msb_rev = true;
     op.has_pack_half_2x16_rtz = true;
+    // Byte packs turn into PRMT.  Pre-Volta F2I can't write 8-bit values so
+    // going through pack_32_4x8 would cost an extra I2I per component there.
+    op.has_pack_32_4x8 = dev.sm >= 70;
     op.has_bfm = dev.sm >= 70;
+    op.has_rotate32 = dev.sm >= 70;
     op.discard_is_demote = true;
 
     op.max_unroll_iterations = 32;
@@ -168,6 +245,43 @@ fn nir_options(dev: &nv_device_info) -> nir_shader_compiler_options {
     op
 }
 
+fn shader_model(dev: &nv_device_info) -> Box<dyn ShaderModel + Send + Sync> {
+    let warps_per_sm = dev.max_warps_per_mp.into();
+    if dev.sm >= 70 {
+        Box::new(ShaderModel70::new(dev.sm, warps_per_sm))
+    } else if dev.sm >= 50 {
+        Box::new(ShaderModel50::new(dev.sm, warps_per_sm))
+    } else {
+        panic!("Unsupported shader model");
+    }
+}
+
+/// The Rust side of a nak_compiler
+///
+/// This is what nak_compiler_create() actually allocates.  The C struct comes
+/// first so a nak_compiler pointer can be cast back to a NakCompiler.
+#[repr(C)]
+struct NakCompiler {
+    nak: nak_compiler,
+    shader_model: Box<dyn ShaderModel + Send + Sync>,
+    stats: CompilerStats,
+}
+
+impl NakCompiler {
+    fn from_ptr<'a>(nak: *const nak_compiler) -> &'a NakCompiler {
+        assert!(!nak.is_null());
+        unsafe { &*(nak as *const NakCompiler) }
+    }
+}
+
+impl std::ops::Deref for NakCompiler {
+    type Target = nak_compiler;
+
+    fn deref(&self) -> &nak_compiler {
+        &self.nak
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn nak_compiler_create(
     dev: *const nv_device_info,
@@ -175,18 +289,22 @@ pub extern "C" fn nak_compiler_create(
     assert!(!dev.is_null());
     let dev = unsafe { &*dev };
 
-    let nak = Box::new(nak_compiler {
-        sm: dev.sm,
-        warps_per_sm: dev.max_warps_per_mp,
-        nir_options: nir_options(dev),
+    let nak = Box::new(NakCompiler {
+        nak: nak_compiler {
+            sm: dev.sm,
+            warps_per_sm: dev.max_warps_per_mp,
+            nir_options: nir_options(dev),
+        },
+        shader_model: shader_model(dev),
+        stats: Default::default(),
     });
 
-    Box::into_raw(nak)
+    Box::into_raw(nak) as *mut nak_compiler
 }
 
 #[no_mangle]
 pub extern "C" fn nak_compiler_destroy(nak: *mut nak_compiler) {
-    unsafe { drop(Box::from_raw(nak)) };
+    unsafe { drop(Box::from_raw(nak as *mut NakCompiler)) };
 }
 
 #[no_mangle]
@@ -194,6 +312,71 @@ pub extern "C" fn nak_debug_flags(_nak: *const nak_compiler) -> u64 {
     DEBUG.debug_flags().into()
 }
 
+#[derive(Default)]
+struct CompilerStats {
+    num_compiles: AtomicU64,
+    num_failed_compiles: AtomicU64,
+    compile_time_ns: AtomicU64,
+    num_spills_to_mem: AtomicU64,
+    num_spills_to_reg: AtomicU64,
+    num_cache_hits: AtomicU64,
+    num_codegen_fallbacks: AtomicU64,
+}
+
+impl CompilerStats {
+    fn record(&self, bin: *const nak_shader_bin, start: Instant) {
+        let time_ns = start.elapsed().as_nanos().try_into().unwrap_or(u64::MAX);
+        self.compile_time_ns.fetch_add(time_ns, Ordering::Relaxed);
+        self.num_compiles.fetch_add(1, Ordering::Relaxed);
+
+        if bin.is_null() {
+            self.num_failed_compiles.fetch_add(1, Ordering::Relaxed);
+        } else {
+            let info = unsafe { &(*bin).info };
+            self.num_spills_to_mem
+                .fetch_add(info.num_spills_to_mem.into(), Ordering::Relaxed);
+            self.num_spills_to_reg
+                .fetch_add(info.num_spills_to_reg.into(), Ordering::Relaxed);
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn nak_compiler_record_cache_hit(nak: *const nak_compiler) {
+    let nak = NakCompiler::from_ptr(nak);
+    nak.stats.num_cache_hits.fetch_add(1, Ordering::Relaxed);
+}
+
+#[no_mangle]
+pub extern "C" fn nak_compiler_record_codegen_fallback(
+    nak: *const nak_compiler,
+) {
+    let nak = NakCompiler::from_ptr(nak);
+    nak.stats
+        .num_codegen_fallbacks
+        .fetch_add(1, Ordering::Relaxed);
+}
+
+#[no_mangle]
+pub extern "C" fn nak_get_compiler_stats(
+    nak: *const nak_compiler,
+    stats: *mut nak_compiler_stats,
+) {
+    let nak = NakCompiler::from_ptr(nak);
+    assert!(!stats.is_null());
+    let stats = unsafe { &mut *stats };
+    let src = &nak.stats;
+
+    stats.num_compiles = src.num_compiles.load(Ordering::Relaxed);
+    stats.num_failed_compiles = src.num_failed_compiles.load(Ordering::Relaxed);
+    stats.compile_time_ns = src.compile_time_ns.load(Ordering::Relaxed);
+    stats.num_spills_to_mem = src.num_spills_to_mem.load(Ordering::Relaxed);
+    stats.num_spills_to_reg = src.num_spills_to_reg.load(Ordering::Relaxed);
+    stats.num_cache_hits = src.num_cache_hits.load(Ordering::Relaxed);
+    stats.num_codegen_fallbacks =
+        src.num_codegen_fallbacks.load(Ordering::Relaxed);
+}
+
 #[no_mangle]
 pub extern "C" fn nak_nir_options(
     nak: *const nak_compiler,
@@ -240,6 +423,11 @@ impl ShaderBin {
             _pad0: Default::default(),
             max_warps_per_sm: info.max_warps_per_sm,
             num_instrs: info.num_instrs,
+            num_fma_instrs: info.num_fma_instrs,
+            num_alu_instrs: info.num_alu_instrs,
+            num_xu_instrs: info.num_xu_instrs,
+            num_mem_instrs: info.num_mem_instrs,
+            num_ctrl_instrs: info.num_ctrl_instrs,
             num_static_cycles: info.num_static_cycles,
             num_spills_to_mem: info.num_spills_to_mem,
             num_fills_from_mem: info.num_fills_from_mem,
@@ -319,6 +507,14 @@ impl ShaderBin {
 
             eprintln!("Stage: {}", stage_name);
             eprintln!("Instruction count: {}", c_info.num_instrs);
+            eprintln!(
+                "Instruction mix: FMA {}, ALU {}, XU {}, MEM {}, CTRL {}",
+                c_info.num_fma_instrs,
+                c_info.num_alu_instrs,
+                c_info.num_xu_instrs,
+                c_info.num_mem_instrs,
+                c_info.num_ctrl_instrs,
+            );
             eprintln!("Static cycle count: {}", c_info.num_static_cycles);
             eprintln!("Max warps/SM: {}", c_info.max_warps_per_sm);
             eprintln!("Spills to mem: {}", c_info.num_spills_to_mem);
@@ -380,90 +576,273 @@ fn eprint_hex(label: &str, data: &[u32]) {
     eprintln!("");
 }
 
+fn eprint_live_ranges(s: &Shader<'_>) {
+    for f in &s.functions {
+        let live = SimpleLiveness::for_function(f);
+        let max_live = live.calc_max_live(f);
+        for file in [RegFile::GPR, RegFile::UGPR, RegFile::Pred, RegFile::UPred]
+        {
+            if max_live[file] == 0 {
+                continue;
+            }
+
+            let mut ranges = String::new();
+            live.write_live_ranges(f, file, &mut ranges)
+                .expect("Failed to write live ranges");
+            eprintln!(
+                "NAK {:?} live ranges (max {}):\n{}",
+                file, max_live[file], ranges
+            );
+        }
+    }
+}
+
 macro_rules! pass {
-    ($s: expr, $pass: ident) => {
-        $s.$pass();
+    ($s: expr, $pass: ident $(, $arg: expr)*) => {
+        let before = DEBUG.diff().then(|| ShaderSnapshot::new(&$s));
+        $s.$pass($($arg),*);
         if DEBUG.print() {
             eprintln!("NAK IR after {}:\n{}", stringify!($pass), $s);
         }
+        if let Some(before) = before {
+            if let Some(diff) = before.diff(&ShaderSnapshot::new(&$s)) {
+                eprintln!(
+                    "NAK IR changes from {}:\n{}",
+                    stringify!($pass),
+                    diff
+                );
+            }
+        }
     };
 }
 
+/// Per-compile parameters
+///
+/// These are all the knobs which may differ between two compiles of the same
+/// NIR shader on the same device.  Device-wide state lives in nak_compiler and
+/// NAK_DEBUG flags are global so neither is included here.
+#[derive(Clone, Copy)]
+pub struct NakCompileParams {
+    /// Whether or not to dump the final assembly into the shader binary
+    pub dump_asm: bool,
+
+    /// Whether floating-point optimizations may ignore signed zero, Inf, and
+    /// NaN preservation
+    pub fast_math: bool,
+
+    /// Optimization level
+    ///
+    /// Zero skips the optional NAK IR optimization passes.  Anything else
+    /// runs all of them.
+    pub opt_level: u8,
+
+    /// Variable modes which require robustBufferAccess2 semantics
+    pub robust2_modes: nir_variable_mode,
+
+    /// Fragment shader key, if any
+    pub fs_key: Option<nak_fs_key>,
+
+    /// Maximum number of GPRs the shader may use, if any
+    ///
+    /// This is in the same units as nak_shader_info::num_gprs so it includes
+    /// the GPRs reserved by the hardware.  Register allocation spills
+    /// anything which does not fit but never goes below its own minimum.
+    pub max_gprs: Option<u32>,
+}
+
+impl Default for NakCompileParams {
+    fn default() -> Self {
+        NakCompileParams {
+            dump_asm: false,
+            fast_math: false,
+            opt_level: NAK_OPT_LEVEL_DEFAULT as u8,
+            robust2_modes: 0,
+            fs_key: None,
+            max_gprs: None,
+        }
+    }
+}
+
+impl NakCompileParams {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn dump_asm(mut self, dump_asm: bool) -> Self {
+        self.dump_asm = dump_asm;
+        self
+    }
+
+    pub fn fast_math(mut self, fast_math: bool) -> Self {
+        self.fast_math = fast_math;
+        self
+    }
+
+    pub fn opt_level(mut self, opt_level: u8) -> Self {
+        self.opt_level = opt_level;
+        self
+    }
+
+    pub fn robust2_modes(mut self, modes: nir_variable_mode) -> Self {
+        self.robust2_modes = modes;
+        self
+    }
+
+    pub fn fs_key(mut self, fs_key: Option<&nak_fs_key>) -> Self {
+        self.fs_key = fs_key.copied();
+        self
+    }
+
+    pub fn max_gprs(mut self, max_gprs: Option<u32>) -> Self {
+        self.max_gprs = max_gprs;
+        self
+    }
+
+    fn fs_key_ptr(&self) -> *const nak_fs_key {
+        match &self.fs_key {
+            Some(key) => key,
+            None => std::ptr::null(),
+        }
+    }
+}
+
+impl fmt::Display for NakCompileParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "dump_asm: {}", self.dump_asm)?;
+        write!(f, ", fast_math: {}", self.fast_math)?;
+        write!(f, ", opt_level: {}", self.opt_level)?;
+        write!(f, ", robust2_modes: {:#x}", self.robust2_modes)?;
+        if let Some(key) = &self.fs_key {
+            write!(
+                f,
+                ", fs_key: {{ zs_self_dep: {}, force_sample_shading: {}, \
+                 uses_underestimate: {}, sample_info_cb: {}, \
+                 sample_locations_offset: {:#x}, \
+                 sample_masks_offset: {:#x} }}",
+                key.zs_self_dep,
+                key.force_sample_shading,
+                key.uses_underestimate,
+                key.sample_info_cb,
+                key.sample_locations_offset,
+                key.sample_masks_offset,
+            )?;
+        }
+        if let Some(max_gprs) = self.max_gprs {
+            write!(f, ", max_gprs: {}", max_gprs)?;
+        }
+        Ok(())
+    }
+}
+
 fn nak_compile_shader_internal(
     nir: *mut nir_shader,
-    dump_asm: bool,
     nak: *const nak_compiler,
-    robust2_modes: nir_variable_mode,
-    fs_key: *const nak_fs_key,
+    params: &NakCompileParams,
 ) -> *mut nak_shader_bin {
-    unsafe { nak_postprocess_nir(nir, nak, robust2_modes, fs_key) };
-    let nak = unsafe { &*nak };
-    let nir = unsafe { &*nir };
-    let fs_key = if fs_key.is_null() {
-        None
-    } else {
-        Some(unsafe { &*fs_key })
+    unsafe {
+        nak_postprocess_nir(
+            nir,
+            nak,
+            params.robust2_modes,
+            params.fast_math,
+            params.fs_key_ptr(),
+        )
     };
+    let nak = NakCompiler::from_ptr(nak);
+    let nir = unsafe { &*nir };
+    let fs_key = params.fs_key.as_ref();
 
-    let sm: Box<dyn ShaderModel> = if nak.sm >= 70 {
-        Box::new(ShaderModel70::new(nak.sm))
-    } else if nak.sm >= 50 {
-        Box::new(ShaderModel50::new(nak.sm))
-    } else {
-        panic!("Unsupported shader model");
-    };
+    if DEBUG.print() {
+        eprintln!("NAK compile params: {}", params);
+    }
+
+    let sm = nak.shader_model.as_ref();
 
-    let mut s = nak_shader_from_nir(nak, nir, sm.as_ref());
+    let mut s = nak_shader_from_nir(nak, nir, sm);
 
     if DEBUG.print() {
         eprintln!("NAK IR:\n{}", &s);
     }
 
-    pass!(s, opt_bar_prop);
+    let optimize = params.opt_level > 0;
+    if optimize {
+        pass!(s, opt_bar_prop);
+        pass!(s, opt_pixld);
+    }
+    // This also moves anything which can't be uniform back to the warp so it
+    // has to run even when not optimizing.
     pass!(s, opt_uniform_instrs);
-    pass!(s, opt_copy_prop);
-    pass!(s, opt_prmt);
-    pass!(s, opt_lop);
-    pass!(s, opt_copy_prop);
+    if optimize {
+        pass!(s, opt_copy_prop);
+        pass!(s, opt_prmt);
+        pass!(s, opt_lop);
+        pass!(s, opt_iadd3);
+        pass!(s, opt_cvt);
+        pass!(s, opt_copy_prop);
+    }
     pass!(s, opt_dce);
-    pass!(s, opt_out);
+    if optimize {
+        pass!(s, opt_out);
+    }
     pass!(s, legalize);
-    pass!(s, assign_regs);
+    if DEBUG.live_ranges() {
+        eprint_live_ranges(&s);
+    }
+    pass!(s, assign_regs, params.max_gprs);
     pass!(s, lower_par_copies);
     pass!(s, lower_copy_swap);
-    if nak.sm >= 70 {
-        pass!(s, opt_jump_thread);
-    } else {
-        pass!(s, opt_crs);
+    if optimize {
+        if nak.sm >= 70 {
+            pass!(s, opt_jump_thread);
+        } else {
+            pass!(s, opt_crs);
+        }
     }
 
     s.remove_annotations();
 
     pass!(s, calc_instr_deps);
 
+    s.validate_regs();
     s.gather_info();
 
     let mut asm = String::new();
-    if dump_asm {
+    if params.dump_asm {
         write!(asm, "{}", s).expect("Failed to dump assembly");
     }
 
     let code = sm.encode_shader(&s);
-    let bin =
-        Box::new(ShaderBin::new(sm.as_ref(), &s.info, fs_key, code, &asm));
+    let bin = Box::new(ShaderBin::new(sm, &s.info, fs_key, code, &asm));
     Box::into_raw(bin) as *mut nak_shader_bin
 }
 
 #[no_mangle]
 pub extern "C" fn nak_compile_shader(
     nir: *mut nir_shader,
-    dump_asm: bool,
     nak: *const nak_compiler,
-    robust2_modes: nir_variable_mode,
-    fs_key: *const nak_fs_key,
+    params: *const nak_compile_params,
 ) -> *mut nak_shader_bin {
-    panic::catch_unwind(|| {
-        nak_compile_shader_internal(nir, dump_asm, nak, robust2_modes, fs_key)
-    })
-    .unwrap_or(std::ptr::null_mut())
+    assert!(!params.is_null());
+    let c_params = unsafe { &*params };
+
+    let fs_key = if c_params.fs_key.is_null() {
+        None
+    } else {
+        Some(unsafe { &*c_params.fs_key })
+    };
+
+    let params = NakCompileParams::new()
+        .dump_asm(c_params.dump_asm)
+        .fast_math(c_params.fast_math)
+        .opt_level(c_params.opt_level)
+        .robust2_modes(c_params.robust2_modes)
+        .fs_key(fs_key)
+        .max_gprs(DEBUG.max_gprs());
+
+    let start = Instant::now();
+    let bin =
+        panic::catch_unwind(|| nak_compile_shader_internal(nir, nak, &params))
+            .unwrap_or(std::ptr::null_mut());
+    NakCompiler::from_ptr(nak).stats.record(bin, start);
+    bin
 }
@@ -1400,8 +1400,38 @@ impl AssignRegsBlock {
     }
 }
 
+/// Returns the smallest GPR count we can spill down to and still succeed at
+/// register allocation.
+fn min_spill_gprs(f: &Function) -> u32 {
+    // We need at least 16 registers to satisfy RA constraints for texture
+    // ops.
+    let mut gprs = 16;
+
+    // OpRegOut can use arbitrarily many GPRs
+    for b in &f.blocks {
+        for instr in b.instrs.iter().rev() {
+            match &instr.op {
+                Op::Exit(_) => (),
+                Op::RegOut(op) => {
+                    let out_gprs = u32::try_from(op.srcs.len()).unwrap();
+                    gprs = max(gprs, out_gprs);
+                }
+                _ => break,
+            }
+        }
+    }
+
+    // and another 2 for parallel copy
+    gprs + 2
+}
+
 impl Shader<'_> {
-    pub fn assign_regs(&mut self) {
+    /// Assigns registers, spilling as needed
+    ///
+    /// If max_gprs is given, it caps the total number of GPRs the shader may
+    /// use, counted the same way as the num_gprs we report to the driver,
+    /// which includes the GPRs reserved by the hardware.
+    pub fn assign_regs(&mut self, max_gprs: Option<u32>) {
         assert!(self.functions.len() == 1);
         let f = &mut self.functions[0];
 
@@ -1437,37 +1467,24 @@ impl Shader<'_> {
         let mut gpr_limit = max(max_live[RegFile::GPR], 16);
         let mut total_gprs = gpr_limit + u32::from(tmp_gprs);
 
+        let forced_gprs = max_gprs;
         let mut max_gprs = self.sm.num_regs(RegFile::GPR);
 
         if DEBUG.spill() {
             // To test spilling, reduce the number of registers to the minimum
-            // practical for RA.  We need at least 16 registers to satisfy RA
-            // constraints for texture ops.
-            max_gprs = 16;
-
-            // OpRegOut can use arbitrarily many GPRs
-            for b in &f.blocks {
-                for instr in b.instrs.iter().rev() {
-                    match &instr.op {
-                        Op::Exit(_) => (),
-                        Op::RegOut(op) => {
-                            let out_gprs =
-                                u32::try_from(op.srcs.len()).unwrap();
-                            max_gprs = max(max_gprs, out_gprs);
-                        }
-                        _ => break,
-                    }
-                }
-            }
-
-            // and another 2 for parallel copy
-            max_gprs += 2;
+            // practical for RA.
+            max_gprs = min_spill_gprs(f);
+        } else if let Some(forced_gprs) = forced_gprs {
+            // Never go below what RA needs to succeed, even if asked to.
+            let forced_gprs =
+                forced_gprs.saturating_sub(self.sm.hw_reserved_gprs());
+            max_gprs = min(max_gprs, max(forced_gprs, min_spill_gprs(f)));
         }
 
         if let ShaderStageInfo::Compute(cs_info) = &self.info.stage {
             max_gprs = min(
                 max_gprs,
-                gpr_limit_from_local_size(&cs_info.local_size)
+                gpr_limit_from_local_size(self.sm, &cs_info.local_size)
                     - self.sm.hw_reserved_gprs(),
             );
         }
@@ -245,6 +245,145 @@ pub trait SSABuilder: Builder {
         dst
     }
 
+    /// Rotates a 32-bit value.  Like NIR, only the bottom 5 bits of the
+    /// shift are used.
+    fn rotate(&mut self, x: Src, shift: Src, right: bool) -> SSARef {
+        // A funnel shift of x:x is a rotate
+        assert!(self.sm() >= 70);
+        let dst = self.alloc_ssa(RegFile::GPR, 1);
+        self.push_op(OpShf {
+            dst: dst.into(),
+            low: x,
+            high: x,
+            shift: shift,
+            right: right,
+            wrap: true,
+            data_type: IntType::U32,
+            dst_high: !right,
+        });
+        dst
+    }
+
+    /// Rotates a 64-bit value.  Like NIR, only the bottom 6 bits of the
+    /// shift are used.
+    fn rotate64(&mut self, x: Src, shift: Src, right: bool) -> SSARef {
+        assert!(self.sm() >= 70);
+        let x = x.as_ssa().unwrap();
+        debug_assert!(shift.src_mod.is_none());
+
+        // Rotating by 32 or more is the same as swapping the halves and
+        // rotating by the rest.
+        let (lo, hi): (Src, Src) = if let SrcRef::Imm32(imm) = shift.src_ref {
+            if imm & 0x20 != 0 {
+                (x[1].into(), x[0].into())
+            } else {
+                (x[0].into(), x[1].into())
+            }
+        } else {
+            let bit5 = self.lop2(LogicOp2::And, shift, 0x20.into());
+            let swap = self.isetp(
+                IntCmpType::U32,
+                IntCmpOp::Ne,
+                bit5.into(),
+                0.into(),
+            );
+            let lo = self.sel(swap.into(), x[1].into(), x[0].into());
+            let hi = self.sel(swap.into(), x[0].into(), x[1].into());
+            (lo.into(), hi.into())
+        };
+
+        // Each half of the result is then a 32-bit funnel shift of one half
+        // into the other.
+        let dst = self.alloc_ssa(RegFile::GPR, 2);
+        for i in 0..2 {
+            let (low, high) = if right == (i == 0) {
+                (lo, hi)
+            } else {
+                (hi, lo)
+            };
+            self.push_op(OpShf {
+                dst: dst[i].into(),
+                low: low,
+                high: high,
+                shift: shift,
+                right: right,
+                wrap: true,
+                data_type: IntType::U32,
+                dst_high: !right,
+            });
+        }
+        dst
+    }
+
+    /// Packs four floats into normalized 8-bit values like NIR's
+    /// pack_unorm_4x8 and pack_snorm_4x8
+    fn pack_norm_4x8(&mut self, srcs: [Src; 4], signed: bool) -> SSARef {
+        // F2I to an 8-bit type only exists on Volta+
+        assert!(self.sm() >= 70);
+
+        let mut bytes = [Src::new_zero(); 4];
+        for (c, byte) in bytes.iter_mut().enumerate() {
+            // F2I saturates to the destination type so only the low end of
+            // snorm needs an explicit clamp.  -128 is out of range.
+            let x = if signed {
+                let dst = self.alloc_ssa(RegFile::GPR, 1);
+                self.push_op(OpFMnMx {
+                    dst: dst.into(),
+                    srcs: [srcs[c], (-1.0_f32).into()],
+                    min: false.into(),
+                    ftz: false,
+                });
+                dst.into()
+            } else {
+                srcs[c]
+            };
+            let scale = if signed { 127.0_f32 } else { 255.0_f32 };
+            let x = self.fmul(x, scale.into());
+
+            let dst = self.alloc_ssa(RegFile::GPR, 1);
+            self.push_op(OpF2I {
+                dst: dst.into(),
+                src: x.into(),
+                src_type: FloatType::F32,
+                dst_type: if signed { IntType::I8 } else { IntType::U8 },
+                rnd_mode: FRndMode::NearestEven,
+                ftz: false,
+            });
+            *byte = dst.into();
+        }
+        self.prmt4(bytes, [0, 4, 8, 12])
+    }
+
+    /// Packs two F32 values into one F16 pair with x in the low half
+    fn pack_half_2x16(&mut self, x: Src, y: Src, rnd_mode: FRndMode) -> SSARef {
+        if self.sm() >= 86 {
+            let dst = self.alloc_ssa(RegFile::GPR, 1);
+            self.push_op(OpF2FP {
+                dst: dst.into(),
+                srcs: [y, x],
+                rnd_mode: rnd_mode,
+            });
+            dst
+        } else {
+            let mut halves = [Src::new_zero(); 2];
+            for (half, src) in halves.iter_mut().zip([x, y]) {
+                let dst = self.alloc_ssa(RegFile::GPR, 1);
+                self.push_op(OpF2F {
+                    dst: dst.into(),
+                    src: src,
+                    src_type: FloatType::F32,
+                    dst_type: FloatType::F16,
+                    rnd_mode: rnd_mode,
+                    ftz: false,
+                    high: false,
+                    integer_rnd: false,
+                });
+                *half = dst.into();
+            }
+            self.prmt(halves[0], halves[1], [0, 1, 4, 5])
+        }
+    }
+
     fn fadd(&mut self, x: Src, y: Src) -> SSARef {
         let dst = self.alloc_ssa(RegFile::GPR, 1);
         self.push_op(OpFAdd {
@@ -1025,3 +1164,100 @@ impl<'a, T: SSABuilder> SSABuilder for UniformBuilder<'a, T> {
         self.b.alloc_ssa(file, comps)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sm70::ShaderModel70;
+
+    /// Builds instructions with `num_srcs` fresh GPR sources and returns
+    /// them as text
+    fn build(
+        sm: u8,
+        num_srcs: usize,
+        f: impl FnOnce(&mut SSAInstrBuilder, &[Src]),
+    ) -> Vec<String> {
+        let sm = ShaderModel70::new(sm, 48);
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let srcs: Vec<Src> = (0..num_srcs)
+            .map(|_| ssa_alloc.alloc(RegFile::GPR).into())
+            .collect();
+        let mut b = SSAInstrBuilder::new(&sm, &mut ssa_alloc);
+        f(&mut b, &srcs);
+        b.as_vec()
+            .iter()
+            .map(|i| i.to_string().trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_pack_unorm_4x8() {
+        let instrs = build(75, 4, |b, x| {
+            b.pack_norm_4x8(x.try_into().unwrap(), false);
+        });
+        assert_eq!(
+            instrs,
+            [
+                "%r5 = fmul %r1 0x437f0000",
+                "%r6 = f2i.u8.f32.re %r5",
+                "%r7 = fmul %r2 0x437f0000",
+                "%r8 = f2i.u8.f32.re %r7",
+                "%r9 = fmul %r3 0x437f0000",
+                "%r10 = f2i.u8.f32.re %r9",
+                "%r11 = fmul %r4 0x437f0000",
+                "%r12 = f2i.u8.f32.re %r11",
+                "%r13 = prmt %r6 [0x40] %r8",
+                "%r14 = prmt %r10 [0x4000] %r12",
+                "%r15 = prmt %r13 [0x7610] %r14",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pack_snorm_4x8() {
+        let instrs = build(75, 4, |b, x| {
+            b.pack_norm_4x8(x.try_into().unwrap(), true);
+        });
+        assert_eq!(
+            instrs,
+            [
+                "%r5 = fmnmx %r1 0xbf800000 pF",
+                "%r6 = fmul %r5 0x42fe0000",
+                "%r7 = f2i.i8.f32.re %r6",
+                "%r8 = fmnmx %r2 0xbf800000 pF",
+                "%r9 = fmul %r8 0x42fe0000",
+                "%r10 = f2i.i8.f32.re %r9",
+                "%r11 = fmnmx %r3 0xbf800000 pF",
+                "%r12 = fmul %r11 0x42fe0000",
+                "%r13 = f2i.i8.f32.re %r12",
+                "%r14 = fmnmx %r4 0xbf800000 pF",
+                "%r15 = fmul %r14 0x42fe0000",
+                "%r16 = f2i.i8.f32.re %r15",
+                "%r17 = prmt %r7 [0x40] %r10",
+                "%r18 = prmt %r13 [0x4000] %r16",
+                "%r19 = prmt %r17 [0x7610] %r18",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pack_half_2x16() {
+        let instrs = build(75, 2, |b, x| {
+            b.pack_half_2x16(x[0], x[1], FRndMode::Zero);
+        });
+        assert_eq!(
+            instrs,
+            [
+                "%r3 = f2f.f16.f32.rz %r1",
+                "%r4 = f2f.f16.f32.rz %r2",
+                "%r5 = prmt %r3 [0x5410] %r4",
+            ]
+        );
+
+        // SM86 has a single instruction for it
+        let instrs = build(86, 2, |b, x| {
+            b.pack_half_2x16(x[0], x[1], FRndMode::NearestEven);
+        });
+        assert_eq!(instrs, ["%r3 = f2fp.pack_ab %r2, %r1"]);
+    }
+}
@@ -302,16 +302,20 @@ impl DepGraph {
     }
 }
 
+// The dependency fields in the instruction encoding can only name this many
+const MAX_BARS: usize = 6;
+
 struct BarAlloc {
     num_bars: u8,
-    bar_dep: [usize; 6],
+    bar_dep: [usize; MAX_BARS],
 }
 
 impl BarAlloc {
-    pub fn new() -> BarAlloc {
+    pub fn new(num_bars: u8) -> BarAlloc {
+        assert!(usize::from(num_bars) <= MAX_BARS);
         BarAlloc {
-            num_bars: 6,
-            bar_dep: [usize::MAX; 6],
+            num_bars: num_bars,
+            bar_dep: [usize::MAX; MAX_BARS],
         }
     }
 
@@ -361,7 +365,68 @@ impl BarAlloc {
     }
 }
 
-fn assign_barriers(f: &mut Function, sm: &dyn ShaderModel) {
+/// Wraps the real latency tables to scoreboard everything and assume
+/// worst-case delays.  This is for NAK_DEBUG=conservative which helps tell
+/// latency table bugs apart from dependency tracking bugs.
+struct ConservativeLatencies<'a> {
+    lat: &'a dyn InstrLatencies,
+}
+
+impl InstrLatencies for ConservativeLatencies<'_> {
+    fn needs_scoreboard(&self, op: &Op) -> bool {
+        // Branches are handled separately by assign_barriers() and never get
+        // scoreboards of their own
+        self.lat.needs_scoreboard(op) || !op.is_branch()
+    }
+
+    fn exec(&self, op: &Op) -> u32 {
+        self.lat.exec(op)
+    }
+
+    fn worst(&self, _write: &Op, _dst_idx: usize) -> u32 {
+        MAX_INSTR_DELAY.into()
+    }
+
+    fn raw(
+        &self,
+        _write: &Op,
+        _dst_idx: usize,
+        _read: &Op,
+        _src_idx: usize,
+    ) -> u32 {
+        MAX_INSTR_DELAY.into()
+    }
+
+    fn war(
+        &self,
+        _read: &Op,
+        _src_idx: usize,
+        _write: &Op,
+        _dst_idx: usize,
+    ) -> u32 {
+        MAX_INSTR_DELAY.into()
+    }
+
+    fn waw(
+        &self,
+        _a: &Op,
+        _a_dst_idx: usize,
+        _b: &Op,
+        _b_dst_idx: usize,
+    ) -> u32 {
+        MAX_INSTR_DELAY.into()
+    }
+
+    fn paw(&self, _write: &Op, _dst_idx: usize) -> u32 {
+        MAX_INSTR_DELAY.into()
+    }
+}
+
+fn assign_barriers(
+    f: &mut Function,
+    sm: &dyn ShaderModel,
+    lat: &dyn InstrLatencies,
+) {
     let mut uses = RegTracker::new_with(&|| RegUse::None);
     let mut deps = DepGraph::new();
 
@@ -379,7 +444,7 @@ fn assign_barriers(f: &mut Function, sm: &dyn ShaderModel) {
                     waits.extend_from_slice(u.deps());
                 });
 
-                if instr.has_fixed_latency(sm.sm()) {
+                if !lat.needs_scoreboard(&instr.op) {
                     // Delays will cover us here.  We just need to make sure
                     // that we wait on any uses that we consume.
                     uses.for_each_instr_src_mut(instr, |_, u| {
@@ -419,7 +484,7 @@ fn assign_barriers(f: &mut Function, sm: &dyn ShaderModel) {
         }
     }
 
-    let mut bars = BarAlloc::new();
+    let mut bars = BarAlloc::new(sm.num_scoreboards());
 
     for (bi, b) in f.blocks.iter_mut().enumerate() {
         for (ip, instr) in b.instrs.iter_mut().enumerate() {
@@ -436,7 +501,7 @@ fn assign_barriers(f: &mut Function, sm: &dyn ShaderModel) {
                 instr.deps.set_yield(true);
             }
 
-            if instr.has_fixed_latency(sm.sm()) {
+            if !lat.needs_scoreboard(&instr.op) {
                 continue;
             }
 
@@ -463,126 +528,7 @@ fn assign_barriers(f: &mut Function, sm: &dyn ShaderModel) {
     }
 }
 
-fn exec_latency(sm: u8, op: &Op) -> u32 {
-    if sm >= 70 {
-        match op {
-            Op::Bar(_) | Op::MemBar(_) => {
-                if sm >= 80 {
-                    6
-                } else {
-                    5
-                }
-            }
-            Op::CCtl(_op) => {
-                // CCTL.C needs 8, CCTL.I needs 11
-                11
-            }
-            // Op::DepBar(_) => 4,
-            _ => 1, // TODO: co-issue
-        }
-    } else {
-        match op {
-            Op::CCtl(_)
-            | Op::MemBar(_)
-            | Op::Bra(_)
-            | Op::SSy(_)
-            | Op::Sync(_)
-            | Op::Brk(_)
-            | Op::PBk(_)
-            | Op::Cont(_)
-            | Op::PCnt(_)
-            | Op::Exit(_)
-            | Op::Bar(_)
-            | Op::Kill(_)
-            | Op::OutFinal(_) => 13,
-            _ => 1,
-        }
-    }
-}
-
-fn instr_latency(sm: u8, op: &Op, dst_idx: usize) -> u32 {
-    let file = match op.dsts_as_slice()[dst_idx] {
-        Dst::None => return 0,
-        Dst::SSA(vec) => vec.file().unwrap(),
-        Dst::Reg(reg) => reg.file(),
-    };
-
-    let (gpr_latency, pred_latency) = if sm < 80 {
-        match op {
-            // Double-precision float ALU
-            Op::DAdd(_)
-            | Op::DFma(_)
-            | Op::DMnMx(_)
-            | Op::DMul(_)
-            | Op::DSetP(_)
-            // Half-precision float ALU
-            | Op::HAdd2(_)
-            | Op::HFma2(_)
-            | Op::HMul2(_)
-            | Op::HSet2(_)
-            | Op::HSetP2(_)
-            | Op::HMnMx2(_) => (13, 14),
-            _ => (6, 13)
-        }
-    } else {
-        (6, 13)
-    };
-
-    // This is BS and we know it
-    match file {
-        RegFile::GPR => gpr_latency,
-        RegFile::UGPR => 12,
-        RegFile::Pred => pred_latency,
-        RegFile::UPred => 11,
-        RegFile::Bar => 0, // Barriers have a HW scoreboard
-        RegFile::Carry => 6,
-        RegFile::Mem => panic!("Not a register"),
-    }
-}
-
-/// Read-after-write latency
-fn raw_latency(
-    sm: u8,
-    write: &Op,
-    dst_idx: usize,
-    _read: &Op,
-    _src_idx: usize,
-) -> u32 {
-    instr_latency(sm, write, dst_idx)
-}
-
-/// Write-after-read latency
-fn war_latency(
-    _sm: u8,
-    _read: &Op,
-    _src_idx: usize,
-    _write: &Op,
-    _dst_idx: usize,
-) -> u32 {
-    // We assume the source gets read in the first 4 cycles.  We don't know how
-    // quickly the write will happen.  This is all a guess.
-    4
-}
-
-/// Write-after-write latency
-fn waw_latency(
-    sm: u8,
-    a: &Op,
-    a_dst_idx: usize,
-    _b: &Op,
-    _b_dst_idx: usize,
-) -> u32 {
-    // We know our latencies are wrong so assume the wrote could happen anywhere
-    // between 0 and instr_latency(a) cycles
-    instr_latency(sm, a, a_dst_idx)
-}
-
-/// Predicate read-after-write latency
-fn paw_latency(_sm: u8, _write: &Op, _dst_idx: usize) -> u32 {
-    13
-}
-
-fn calc_delays(f: &mut Function, sm: &dyn ShaderModel) {
+fn calc_delays(f: &mut Function, lat: &dyn InstrLatencies) {
     for b in f.blocks.iter_mut().rev() {
         let mut cycle = 0_u32;
 
@@ -596,11 +542,11 @@ fn calc_delays(f: &mut Function, sm: &dyn ShaderModel) {
             RegTracker::new_with(&|| RegUse::None);
 
         // Map from barrier to last waited cycle
-        let mut bars = [0_u32; 6];
+        let mut bars = [0_u32; MAX_BARS];
 
         for ip in (0..b.instrs.len()).rev() {
             let instr = &b.instrs[ip];
-            let mut min_start = cycle + exec_latency(sm.sm(), &instr.op);
+            let mut min_start = cycle + lat.exec(&instr.op);
             if let Some(bar) = instr.deps.rd_bar() {
                 min_start = max(min_start, bars[usize::from(bar)] + 2);
             }
@@ -612,13 +558,12 @@ fn calc_delays(f: &mut Function, sm: &dyn ShaderModel) {
                     // We don't know how it will be used but it may be used in
                     // the next block so we need at least assume the maximum
                     // destination latency from the end of the block.
-                    let s = instr_latency(sm.sm(), &instr.op, i);
+                    let s = lat.worst(&instr.op, i);
                     min_start = max(min_start, s);
                 }
                 RegUse::Write((w_ip, w_dst_idx)) => {
                     let s = instr_cycle[*w_ip]
-                        + waw_latency(
-                            sm.sm(),
+                        + lat.waw(
                             &instr.op,
                             i,
                             &b.instrs[*w_ip].op,
@@ -630,10 +575,9 @@ fn calc_delays(f: &mut Function, sm: &dyn ShaderModel) {
                     for (r_ip, r_src_idx) in reads {
                         let c = instr_cycle[*r_ip];
                         let s = if *r_src_idx == usize::MAX {
-                            c + paw_latency(sm.sm(), &instr.op, i)
+                            c + lat.paw(&instr.op, i)
                         } else {
-                            c + raw_latency(
-                                sm.sm(),
+                            c + lat.raw(
                                 &instr.op,
                                 i,
                                 &b.instrs[*r_ip].op,
@@ -648,8 +592,7 @@ fn calc_delays(f: &mut Function, sm: &dyn ShaderModel) {
                 RegUse::None => (),
                 RegUse::Write((w_ip, w_dst_idx)) => {
                     let s = instr_cycle[*w_ip]
-                        + war_latency(
-                            sm.sm(),
+                        + lat.war(
                             &instr.op,
                             i,
                             &b.instrs[*w_ip].op,
@@ -697,7 +640,7 @@ fn calc_delays(f: &mut Function, sm: &dyn ShaderModel) {
         if matches!(instr.op, Op::SrcBar(_)) {
             instr.op = Op::Nop(OpNop { label: None });
             MappedInstrs::One(instr)
-        } else if exec_latency(sm.sm(), &instr.op) > 1 {
+        } else if lat.exec(&instr.op) > 1 {
             let mut nop = Instr::new_boxed(OpNop { label: None });
             nop.deps.set_delay(2);
             MappedInstrs::Many(vec![instr, nop])
@@ -707,6 +650,216 @@ fn calc_delays(f: &mut Function, sm: &dyn ShaderModel) {
     });
 }
 
+#[derive(Clone)]
+struct VerifyRegUse {
+    /// The last write in this block as (ip, dst_idx)
+    write: Option<(usize, usize)>,
+
+    /// Reads since the last write as (ip, src_idx).  Predicates are
+    /// represented by src_idx = usize::MAX.
+    reads: Vec<(usize, usize)>,
+}
+
+/// Re-checks the delays and scoreboards calculated by assign_barriers() and
+/// calc_delays() against the latency tables
+///
+/// This only looks at dependencies within a block.  Dependencies across
+/// blocks are handled with worst-case latencies at the end of the block and
+/// scoreboards so that's all we check for them.
+fn verify_deps(f: &Function, lat: &dyn InstrLatencies) {
+    for (bi, b) in f.blocks.iter().enumerate() {
+        // Issue cycle of each instruction relative to the top of the block
+        let mut cycle = Vec::with_capacity(b.instrs.len() + 1);
+        cycle.push(0_u32);
+        for instr in &b.instrs {
+            let c = *cycle.last().unwrap();
+            cycle.push(c + u32::from(instr.deps.delay));
+        }
+        let end_cycle = cycle[b.instrs.len()];
+
+        let mut uses = RegTracker::new_with(&|| VerifyRegUse {
+            write: None,
+            reads: Vec::new(),
+        });
+
+        // The last IP to wait on and to set each scoreboard
+        let mut last_wait: [Option<usize>; MAX_BARS] = [None; MAX_BARS];
+        let mut last_set: [Option<usize>; MAX_BARS] = [None; MAX_BARS];
+
+        let waited_since = |last_wait: &[Option<usize>; MAX_BARS],
+                            bar: Option<u8>,
+                            ip: usize| {
+            bar.is_some_and(|bar| {
+                last_wait[usize::from(bar)].is_some_and(|w| w > ip)
+            })
+        };
+
+        for (ip, instr) in b.instrs.iter().enumerate() {
+            let check = |ok: bool, what: &str, other: usize| {
+                assert!(
+                    ok,
+                    "Unsatisfied {what} dependency in block {bi} from \
+                     {} to {}",
+                    b.instrs[other], instr,
+                );
+            };
+
+            for bar in 0..MAX_BARS {
+                if instr.deps.wt_bar_mask & (1 << bar) == 0 {
+                    continue;
+                }
+                if let Some(s_ip) = last_set[bar] {
+                    check(cycle[ip] >= cycle[s_ip] + 2, "scoreboard", s_ip);
+                }
+                last_wait[bar] = Some(ip);
+            }
+
+            uses.for_each_instr_pred_mut(instr, |u| {
+                let Some((w_ip, w_dst_idx)) = u.write else {
+                    return;
+                };
+                let w_op = &b.instrs[w_ip].op;
+                let delay = lat.paw(w_op, w_dst_idx);
+                check(cycle[ip] >= cycle[w_ip] + delay, "paw", w_ip);
+                if lat.needs_scoreboard(w_op) {
+                    let wr_bar = b.instrs[w_ip].deps.wr_bar();
+                    check(waited_since(&last_wait, wr_bar, w_ip), "paw", w_ip);
+                }
+            });
+
+            uses.for_each_instr_src_mut(instr, |i, u| {
+                let Some((w_ip, w_dst_idx)) = u.write else {
+                    return;
+                };
+                let w_op = &b.instrs[w_ip].op;
+                let delay = lat.raw(w_op, w_dst_idx, &instr.op, i);
+                check(cycle[ip] >= cycle[w_ip] + delay, "raw", w_ip);
+                if lat.needs_scoreboard(w_op) {
+                    let wr_bar = b.instrs[w_ip].deps.wr_bar();
+                    check(waited_since(&last_wait, wr_bar, w_ip), "raw", w_ip);
+                }
+            });
+
+            uses.for_each_instr_dst_mut(instr, |i, u| {
+                // Like calc_delays(), only order a write against the previous
+                // one if nothing read the register in between.  Otherwise,
+                // the reads are what we wait on.
+                let prev_write = u.write.filter(|_| u.reads.is_empty());
+                if let Some((w_ip, w_dst_idx)) = prev_write {
+                    let w_op = &b.instrs[w_ip].op;
+                    let delay = lat.waw(w_op, w_dst_idx, &instr.op, i);
+                    check(cycle[ip] >= cycle[w_ip] + delay, "waw", w_ip);
+                    if lat.needs_scoreboard(w_op) {
+                        let wr_bar = b.instrs[w_ip].deps.wr_bar();
+                        let ok = waited_since(&last_wait, wr_bar, w_ip);
+                        check(ok, "waw", w_ip);
+                    }
+                }
+
+                for (r_ip, r_src_idx) in &u.reads {
+                    if *r_ip == ip || *r_src_idx == usize::MAX {
+                        continue;
+                    }
+                    let r_op = &b.instrs[*r_ip].op;
+                    let delay = lat.war(r_op, *r_src_idx, &instr.op, i);
+                    check(cycle[ip] >= cycle[*r_ip] + delay, "war", *r_ip);
+                    if lat.needs_scoreboard(r_op) {
+                        let rd_bar = b.instrs[*r_ip].deps.rd_bar();
+                        let ok = waited_since(&last_wait, rd_bar, *r_ip);
+                        check(ok, "war", *r_ip);
+                    }
+                }
+
+                u.write = Some((ip, i));
+                u.reads.clear();
+            });
+
+            uses.for_each_instr_pred_mut(instr, |u| {
+                u.reads.push((ip, usize::MAX));
+            });
+            uses.for_each_instr_src_mut(instr, |i, u| {
+                u.reads.push((ip, i));
+            });
+
+            let bars = [instr.deps.rd_bar(), instr.deps.wr_bar()];
+            for bar in bars.into_iter().flatten() {
+                last_set[usize::from(bar)] = Some(ip);
+            }
+        }
+
+        // Values which aren't used again in this block may be used by the
+        // next block so they need to be ready by the end of this one.
+        for instr_ip in 0..b.instrs.len() {
+            let instr = &b.instrs[instr_ip];
+            uses.for_each_instr_dst_mut(instr, |i, u| {
+                if u.write == Some((instr_ip, i)) && u.reads.is_empty() {
+                    let delay = lat.worst(&instr.op, i);
+                    assert!(
+                        end_cycle >= cycle[instr_ip] + delay,
+                        "Result of {instr} not ready at the end of block {bi}",
+                    );
+                }
+            });
+        }
+    }
+}
+
+/// Prints the instruction count, issue cycles and critical path of each block
+///
+/// The critical path only follows RAW and paw dependencies within the block
+/// so it is a lower bound on how fast the block could run.  If it is longer
+/// than the instruction count, no amount of reordering will keep the block
+/// from stalling and it is latency bound.
+fn eprint_block_ilp(f: &Function, lat: &dyn InstrLatencies) {
+    for b in &f.blocks {
+        // The (ip, dst_idx) of the last write to each register
+        let mut writes: RegTracker<Option<(usize, usize)>> =
+            RegTracker::new_with(&|| None);
+
+        let mut start = Vec::with_capacity(b.instrs.len());
+        let mut num_instrs = 0_u32;
+        let mut issue_cycles = 0_u32;
+        let mut critical_path = 0_u32;
+        for (ip, instr) in b.instrs.iter().enumerate() {
+            let mut s = 0_u32;
+            writes.for_each_instr_pred_mut(instr, |w| {
+                if let Some((w_ip, w_dst_idx)) = *w {
+                    let w_op = &b.instrs[w_ip].op;
+                    s = max(s, start[w_ip] + lat.paw(w_op, w_dst_idx));
+                }
+            });
+            writes.for_each_instr_src_mut(instr, |i, w| {
+                if let Some((w_ip, w_dst_idx)) = *w {
+                    let w_op = &b.instrs[w_ip].op;
+                    let delay = lat.raw(w_op, w_dst_idx, &instr.op, i);
+                    s = max(s, start[w_ip] + delay);
+                }
+            });
+            writes.for_each_instr_dst_mut(instr, |i, w| {
+                *w = Some((ip, i));
+            });
+            start.push(s);
+
+            if !matches!(instr.op, Op::Nop(_)) {
+                num_instrs += 1;
+            }
+            issue_cycles += u32::from(instr.deps.delay);
+            critical_path = max(critical_path, s + lat.exec(&instr.op));
+        }
+
+        let bound = if critical_path > num_instrs {
+            "latency"
+        } else {
+            "issue"
+        };
+        eprintln!(
+            "block {}: {} instrs, {} issue cycles, critical path {} cycles \
+             ({} bound)",
+            b.label, num_instrs, issue_cycles, critical_path, bound,
+        );
+    }
+}
+
 impl Shader<'_> {
     pub fn assign_deps_serial(&mut self) {
         for f in &mut self.functions {
@@ -743,10 +896,471 @@ impl Shader<'_> {
         if DEBUG.serial() {
             self.assign_deps_serial();
         } else {
+            let conservative = ConservativeLatencies {
+                lat: self.sm.latencies(),
+            };
+            let lat: &dyn InstrLatencies = if DEBUG.conservative() {
+                &conservative
+            } else {
+                self.sm.latencies()
+            };
+
             for f in &mut self.functions {
-                assign_barriers(f, self.sm);
-                calc_delays(f, self.sm);
+                assign_barriers(f, self.sm, lat);
+                calc_delays(f, lat);
+                if cfg!(debug_assertions) {
+                    verify_deps(f, lat);
+                }
+                if DEBUG.ilp() {
+                    eprint_block_ilp(f, lat);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        assign_barriers, calc_delays, verify_deps, ConservativeLatencies,
+    };
+    use crate::ir::*;
+    use crate::sm50::ShaderModel50;
+    use crate::sm70::ShaderModel70;
+    use compiler::cfg::CFG;
+
+    // None of these tests look at occupancy so the warp count is arbitrary
+    fn sm50(sm: u8) -> ShaderModel50 {
+        ShaderModel50::new(sm, 64)
+    }
+
+    fn sm70(sm: u8) -> ShaderModel70 {
+        ShaderModel70::new(sm, 64)
+    }
+
+    fn imad() -> Op {
+        Op::IMad(OpIMad {
+            dst: Dst::None,
+            srcs: [Src::new_zero(), Src::new_zero(), Src::new_zero()],
+            signed: false,
+        })
+    }
+
+    fn dadd() -> Op {
+        Op::DAdd(OpDAdd {
+            dst: Dst::None,
+            srcs: [Src::new_zero(), Src::new_zero()],
+            rnd_mode: FRndMode::NearestEven,
+        })
+    }
+
+    fn hadd2() -> Op {
+        Op::HAdd2(OpHAdd2 {
+            dst: Dst::None,
+            srcs: [Src::new_zero(), Src::new_zero()],
+            saturate: false,
+            ftz: false,
+            f32: false,
+        })
+    }
+
+    fn iadd3(file: RegFile, pred_file: RegFile) -> Op {
+        Op::IAdd3(OpIAdd3 {
+            dst: RegRef::new(file, 0, 1).into(),
+            overflow: [
+                RegRef::new(pred_file, 0, 1).into(),
+                RegRef::new(pred_file, 1, 1).into(),
+            ],
+            srcs: [Src::new_zero(), Src::new_zero(), Src::new_zero()],
+        })
+    }
+
+    fn dsetp() -> Op {
+        Op::DSetP(OpDSetP {
+            dst: RegRef::new(RegFile::Pred, 0, 1).into(),
+            set_op: PredSetOp::And,
+            cmp_op: FloatCmpOp::OrdLt,
+            srcs: [Src::new_zero(), Src::new_zero()],
+            accum: true.into(),
+        })
+    }
+
+    fn check_latencies_sane(lat: &dyn InstrLatencies, ops: &[Op]) {
+        // Delays are encoded in a 4-bit field
+        const MAX_DELAY: u32 = 15;
+
+        for w in ops {
+            let exec = lat.exec(w);
+            assert!(exec > 0 && exec <= MAX_DELAY);
+            assert!(lat.paw(w, 0) <= MAX_DELAY);
+
+            for (d, dst) in w.dsts_as_slice().iter().enumerate() {
+                if dst.is_none() {
+                    continue;
+                }
+
+                // Barriers have a hardware scoreboard
+                if dst.as_reg().is_some_and(|r| r.file() == RegFile::Bar) {
+                    assert!(lat.worst(w, d) == 0);
+                    continue;
+                }
+
+                assert!(lat.worst(w, d) > 0 && lat.worst(w, d) <= MAX_DELAY);
+                for r in ops {
+                    for s in 0..r.srcs_as_slice().len() {
+                        let raw = lat.raw(w, d, r, s);
+                        assert!(raw > 0 && raw <= lat.worst(w, d));
+                        let war = lat.war(r, s, w, d);
+                        assert!(war > 0 && war <= MAX_DELAY);
+                    }
+                    for rd in 0..r.dsts_as_slice().len() {
+                        assert!(lat.waw(w, d, r, rd) <= MAX_DELAY);
+                    }
+                }
             }
         }
     }
+
+    /// Returns one op for every class the latency tables distinguish: each
+    /// fp64 and fp16 op, plain fixed-latency ALU ops, variable-latency ops,
+    /// ops with a special exec latency, and writes to every register file.
+    fn latency_class_ops(has_uniform: bool) -> Vec<Op> {
+        let gpr = |comps| Dst::from(RegRef::new(RegFile::GPR, 0, comps));
+        let pred = || Dst::from(RegRef::new(RegFile::Pred, 0, 1));
+        let zero = Src::new_zero;
+        let target = LabelAllocator::new().alloc();
+
+        let mut ops = vec![
+            // Double-precision float ALU
+            Op::DAdd(OpDAdd {
+                dst: gpr(2),
+                srcs: [zero(), zero()],
+                rnd_mode: FRndMode::NearestEven,
+            }),
+            Op::DFma(OpDFma {
+                dst: gpr(2),
+                srcs: [zero(), zero(), zero()],
+                rnd_mode: FRndMode::NearestEven,
+            }),
+            Op::DMnMx(OpDMnMx {
+                dst: gpr(2),
+                srcs: [zero(), zero()],
+                min: true.into(),
+            }),
+            Op::DMul(OpDMul {
+                dst: gpr(2),
+                srcs: [zero(), zero()],
+                rnd_mode: FRndMode::NearestEven,
+            }),
+            dsetp(),
+            // Half-precision float ALU
+            Op::HAdd2(OpHAdd2 {
+                dst: gpr(1),
+                srcs: [zero(), zero()],
+                saturate: false,
+                ftz: false,
+                f32: false,
+            }),
+            Op::HFma2(OpHFma2 {
+                dst: gpr(1),
+                srcs: [zero(), zero(), zero()],
+                saturate: false,
+                ftz: false,
+                dnz: false,
+                f32: false,
+            }),
+            Op::HMul2(OpHMul2 {
+                dst: gpr(1),
+                srcs: [zero(), zero()],
+                saturate: false,
+                ftz: false,
+                dnz: false,
+            }),
+            Op::HSet2(OpHSet2 {
+                dst: gpr(1),
+                set_op: PredSetOp::And,
+                cmp_op: FloatCmpOp::OrdLt,
+                srcs: [zero(), zero()],
+                accum: true.into(),
+                ftz: false,
+            }),
+            Op::HSetP2(OpHSetP2 {
+                dsts: [pred(), Dst::None],
+                set_op: PredSetOp::And,
+                cmp_op: FloatCmpOp::OrdLt,
+                srcs: [zero(), zero()],
+                accum: true.into(),
+                ftz: false,
+                horizontal: false,
+            }),
+            Op::HMnMx2(OpHMnMx2 {
+                dst: gpr(1),
+                srcs: [zero(), zero()],
+                min: true.into(),
+                ftz: false,
+            }),
+            // Everything else with a fixed latency
+            iadd3(RegFile::GPR, RegFile::Pred),
+            Op::FAdd(OpFAdd {
+                dst: gpr(1),
+                srcs: [zero(), zero()],
+                saturate: false,
+                rnd_mode: FRndMode::NearestEven,
+                ftz: false,
+            }),
+            Op::IMad(OpIMad {
+                dst: gpr(1),
+                srcs: [zero(), zero(), zero()],
+                signed: false,
+            }),
+            Op::IAdd2(OpIAdd2 {
+                dst: gpr(1),
+                carry_out: RegRef::new(RegFile::Carry, 0, 1).into(),
+                srcs: [zero(), zero()],
+            }),
+            Op::BMov(OpBMov {
+                dst: RegRef::new(RegFile::Bar, 0, 1).into(),
+                src: zero(),
+                clear: false,
+            }),
+            // Variable latency
+            Op::MuFu(OpMuFu {
+                dst: gpr(1),
+                op: MuFuOp::Rcp,
+                src: zero(),
+            }),
+            // Special exec latencies
+            Op::Bar(OpBar {}),
+            Op::MemBar(OpMemBar {
+                scope: MemScope::GPU,
+            }),
+            Op::CCtl(OpCCtl {
+                op: CCtlOp::IV,
+                mem_space: MemSpace::Shared,
+                addr: zero(),
+                addr_offset: 0,
+            }),
+            Op::Bra(OpBra { target: target }),
+            Op::SSy(OpSSy { target: target }),
+            Op::Sync(OpSync { target: target }),
+            Op::Brk(OpBrk { target: target }),
+            Op::PBk(OpPBk { target: target }),
+            Op::Cont(OpCont { target: target }),
+            Op::PCnt(OpPCnt { target: target }),
+            Op::Exit(OpExit {}),
+            Op::Kill(OpKill {}),
+            Op::OutFinal(OpOutFinal { handle: zero() }),
+        ];
+        if has_uniform {
+            ops.push(iadd3(RegFile::UGPR, RegFile::UPred));
+        }
+        ops
+    }
+
+    #[test]
+    fn test_latencies_sane() {
+        for sm in [50, 52, 53, 60, 61, 62] {
+            let ops = latency_class_ops(false);
+            check_latencies_sane(sm50(sm).latencies(), &ops);
+        }
+
+        for sm in [70, 72, 75, 80, 86, 87, 89, 90, 120] {
+            let ops = latency_class_ops(sm >= 75);
+            check_latencies_sane(sm70(sm).latencies(), &ops);
+        }
+    }
+
+    #[test]
+    fn test_unknown_sm_latencies() {
+        // Unknown SMs fall back to the newest known one plus a margin
+        let known = sm70(89);
+        let unknown = sm70(120);
+        let op = iadd3(RegFile::GPR, RegFile::Pred);
+        for i in 0..op.dsts_as_slice().len() {
+            let k = known.latencies().worst(&op, i);
+            let u = unknown.latencies().worst(&op, i);
+            assert!(u > k);
+        }
+    }
+
+    #[test]
+    fn test_dep_cost() {
+        let sm = sm70(80);
+        let x = SSAValue::new(RegFile::GPR, 1);
+        let y = SSAValue::new(RegFile::GPR, 2);
+
+        let write = Instr::new(OpIAdd3 {
+            dst: x.into(),
+            overflow: [Dst::None; 2],
+            srcs: [Src::new_zero(), Src::new_zero(), Src::new_zero()],
+        });
+        let read = |src: SSAValue| {
+            Instr::new(OpIAdd3 {
+                dst: Dst::None,
+                overflow: [Dst::None; 2],
+                srcs: [src.into(), Src::new_zero(), Src::new_zero()],
+            })
+        };
+
+        let lat = sm.latencies().raw(&write.op, 0, &read(x).op, 0);
+        assert_eq!(sm.dep_cost(&write, &read(x)), lat);
+        assert_eq!(sm.dep_cost(&write, &read(y)), 0);
+    }
+
+    #[test]
+    fn test_imad_scoreboard() {
+        assert!(sm50(50).latencies().needs_scoreboard(&imad()));
+        assert!(sm50(61).latencies().needs_scoreboard(&imad()));
+        for sm in [70, 75, 80, 86, 89] {
+            assert!(!sm70(sm).latencies().needs_scoreboard(&imad()));
+        }
+    }
+
+    #[test]
+    fn test_turing_ampere_scoreboards_match() {
+        // fp64 and fp16 are both redirected on Turing but only fp64 is
+        // variable-latency.  Ampere doesn't redirect fp16 at all.  That only
+        // shows up in the fixed latencies; the scoreboard sets are the same.
+        let turing = sm70(75);
+        for sm in [75, 80, 86] {
+            let sm = sm70(sm);
+            assert!(sm.latencies().needs_scoreboard(&dadd()));
+            assert!(!sm.latencies().needs_scoreboard(&hadd2()));
+        }
+
+        let mut hadd2_reg = hadd2();
+        if let Op::HAdd2(op) = &mut hadd2_reg {
+            op.dst = RegRef::new(RegFile::GPR, 0, 1).into();
+        }
+        let ampere = sm70(80);
+        let t = turing.latencies().worst(&hadd2_reg, 0);
+        let a = ampere.latencies().worst(&hadd2_reg, 0);
+        assert!(t > a);
+    }
+
+    /// Wraps real latencies but makes every write-after-write dependency
+    /// as long as possible
+    struct LongWawLatencies<'a> {
+        lat: &'a dyn InstrLatencies,
+    }
+
+    impl InstrLatencies for LongWawLatencies<'_> {
+        fn needs_scoreboard(&self, op: &Op) -> bool {
+            self.lat.needs_scoreboard(op)
+        }
+
+        fn exec(&self, op: &Op) -> u32 {
+            self.lat.exec(op)
+        }
+
+        fn worst(&self, write: &Op, dst_idx: usize) -> u32 {
+            self.lat.worst(write, dst_idx)
+        }
+
+        fn raw(&self, w: &Op, dst_idx: usize, r: &Op, src_idx: usize) -> u32 {
+            self.lat.raw(w, dst_idx, r, src_idx)
+        }
+
+        fn war(&self, r: &Op, src_idx: usize, w: &Op, dst_idx: usize) -> u32 {
+            self.lat.war(r, src_idx, w, dst_idx)
+        }
+
+        fn waw(&self, _a: &Op, _a_idx: usize, _b: &Op, _b_idx: usize) -> u32 {
+            MAX_INSTR_DELAY.into()
+        }
+
+        fn paw(&self, write: &Op, dst_idx: usize) -> u32 {
+            self.lat.paw(write, dst_idx)
+        }
+    }
+
+    /// Builds a single-block function of IAdd3s, one per (dst, src) pair
+    fn add_block(adds: &[(RegRef, Src)]) -> Function {
+        let mut instrs: Vec<_> = adds
+            .iter()
+            .map(|(dst, src)| {
+                Instr::new_boxed(OpIAdd3 {
+                    dst: (*dst).into(),
+                    overflow: [Dst::None; 2],
+                    srcs: [*src, Src::new_zero(), Src::new_zero()],
+                })
+            })
+            .collect();
+        instrs.push(Instr::new_boxed(OpExit {}));
+
+        let block = BasicBlock {
+            label: LabelAllocator::new().alloc(),
+            uniform: true,
+            instrs,
+        };
+
+        Function {
+            ssa_alloc: SSAValueAllocator::new(),
+            phi_alloc: PhiAllocator::new(),
+            blocks: CFG::from_blocks_edges([block], std::iter::empty()),
+        }
+    }
+
+    fn add_chain() -> Function {
+        let r0 = RegRef::new(RegFile::GPR, 0, 1);
+        let r1 = RegRef::new(RegFile::GPR, 1, 1);
+        add_block(&[(r0, Src::new_zero()), (r1, r0.into())])
+    }
+
+    #[test]
+    fn test_verify_deps() {
+        let sm = sm70(80);
+        let mut f = add_chain();
+        assign_barriers(&mut f, &sm, sm.latencies());
+        calc_delays(&mut f, sm.latencies());
+        verify_deps(&f, sm.latencies());
+    }
+
+    #[test]
+    fn test_conservative_deps() {
+        let sm = sm70(80);
+        let lat = ConservativeLatencies {
+            lat: sm.latencies(),
+        };
+        let mut f = add_chain();
+        assign_barriers(&mut f, &sm, &lat);
+        calc_delays(&mut f, &lat);
+        verify_deps(&f, &lat);
+
+        // Everything but the branch is scoreboarded
+        let instrs = &f.blocks[0].instrs;
+        assert!(instrs[0].deps.wr_bar().is_some());
+        assert!(instrs[1].deps.wt_bar_mask != 0);
+    }
+
+    #[test]
+    fn test_verify_deps_waw_after_read() {
+        // r0 is read between the two writes so calc_delays() only orders the
+        // second write against the read and verify_deps() has to agree.
+        let sm = sm70(80);
+        let lat = LongWawLatencies {
+            lat: sm.latencies(),
+        };
+        let r0 = RegRef::new(RegFile::GPR, 0, 1);
+        let r1 = RegRef::new(RegFile::GPR, 1, 1);
+        let mut f = add_block(&[
+            (r0, Src::new_zero()),
+            (r1, r0.into()),
+            (r0, Src::new_imm_u32(1)),
+        ]);
+        assign_barriers(&mut f, &sm, &lat);
+        calc_delays(&mut f, &lat);
+        verify_deps(&f, &lat);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsatisfied raw dependency")]
+    fn test_verify_deps_short_delay() {
+        let sm = sm70(80);
+        let mut f = add_chain();
+        assign_barriers(&mut f, &sm, sm.latencies());
+        calc_delays(&mut f, sm.latencies());
+        f.blocks[0].instrs[0].deps.set_delay(MIN_INSTR_DELAY);
+        verify_deps(&f, sm.latencies());
+    }
 }
@@ -24,6 +24,11 @@ fn init_info_from_nir(nak: &nak_compiler, nir: &nir_shader) -> ShaderInfo {
         max_warps_per_sm: 0,
         num_gprs: 0,
         num_instrs: 0,
+        num_fma_instrs: 0,
+        num_alu_instrs: 0,
+        num_xu_instrs: 0,
+        num_mem_instrs: 0,
+        num_ctrl_instrs: 0,
         num_static_cycles: 0,
         num_spills_to_mem: 0,
         num_fills_from_mem: 0,
@@ -610,6 +615,19 @@ impl<'a> ShaderFromNir<'a> {
                 self.set_ssa(&alu.def, comps);
                 return;
             }
+            nir_op_pack_unorm_4x8 | nir_op_pack_snorm_4x8 => {
+                // These take a vec4 of 32-bit floats which the generic
+                // source handling below doesn't allow
+                let src = alu.get_src(0);
+                let ssa = self.get_ssa(src.src.as_def());
+                let srcs = std::array::from_fn(|c| {
+                    Src::from(ssa[usize::from(src.swizzle[c])])
+                });
+                let signed = alu.op == nir_op_pack_snorm_4x8;
+                let dst = b.pack_norm_4x8(srcs, signed);
+                self.set_dst(&alu.def, dst);
+                return;
+            }
             _ => (),
         }
 
@@ -1502,6 +1520,7 @@ impl<'a> ShaderFromNir<'a> {
             nir_op_ixor => b.lop2(LogicOp2::Xor, srcs[0], srcs[1]),
             nir_op_pack_half_2x16_split | nir_op_pack_half_2x16_rtz_split => {
                 assert!(alu.get_src(0).bit_size() == 32);
+                assert!(alu.get_src(1).bit_size() == 32);
 
                 let rnd_mode = match alu.op {
                     nir_op_pack_half_2x16_split => FRndMode::NearestEven,
@@ -1509,46 +1528,7 @@ impl<'a> ShaderFromNir<'a> {
                     _ => panic!("Unhandled fp16 pack op"),
                 };
 
-                if self.sm.sm() >= 86 {
-                    let result: SSARef = b.alloc_ssa(RegFile::GPR, 1);
-                    b.push_op(OpF2FP {
-                        dst: result.into(),
-                        srcs: [srcs[1], srcs[0]],
-                        rnd_mode: rnd_mode,
-                    });
-
-                    result
-                } else {
-                    let low = b.alloc_ssa(RegFile::GPR, 1);
-                    let high = b.alloc_ssa(RegFile::GPR, 1);
-
-                    b.push_op(OpF2F {
-                        dst: low.into(),
-                        src: srcs[0],
-                        src_type: FloatType::F32,
-                        dst_type: FloatType::F16,
-                        rnd_mode: rnd_mode,
-                        ftz: false,
-                        high: false,
-                        integer_rnd: false,
-                    });
-
-                    let src_bits = usize::from(alu.get_src(1).bit_size());
-                    let src_type = FloatType::from_bits(src_bits);
-                    assert!(matches!(src_type, FloatType::F32));
-                    b.push_op(OpF2F {
-                        dst: high.into(),
-                        src: srcs[1],
-                        src_type: FloatType::F32,
-                        dst_type: FloatType::F16,
-                        rnd_mode: rnd_mode,
-                        ftz: false,
-                        high: false,
-                        integer_rnd: false,
-                    });
-
-                    b.prmt(low.into(), high.into(), [0, 1, 4, 5])
-                }
+                b.pack_half_2x16(srcs[0], srcs[1], rnd_mode)
             }
             nir_op_prmt_nv => {
                 let dst = b.alloc_ssa(RegFile::GPR, 1);
@@ -1695,6 +1675,20 @@ impl<'a> ShaderFromNir<'a> {
 
                 dst
             }
+            nir_op_urol | nir_op_uror => {
+                let right = alu.op == nir_op_uror;
+                if alu.def.bit_size() == 64 {
+                    let shift = if let Some(s) = nir_srcs[1].comp_as_uint(0) {
+                        (s as u32).into()
+                    } else {
+                        srcs[1]
+                    };
+                    b.rotate64(srcs[0], shift, right)
+                } else {
+                    assert!(alu.def.bit_size() == 32);
+                    b.rotate(srcs[0], srcs[1], right)
+                }
+            }
             nir_op_ushr => {
                 if alu.def.bit_size() == 64 {
                     let shift = if let Some(s) = nir_srcs[1].comp_as_uint(0) {
@@ -32,10 +32,11 @@ impl RunSingleton {
 
             let run = Runner::new(dev_id);
             let sm_nr = run.dev_info().sm;
+            let warps_per_sm = run.dev_info().max_warps_per_mp.into();
             let sm: Box<dyn ShaderModel + Send + Sync> = if sm_nr >= 70 {
-                Box::new(ShaderModel70::new(sm_nr))
+                Box::new(ShaderModel70::new(sm_nr, warps_per_sm))
             } else if sm_nr >= 50 {
-                Box::new(ShaderModel50::new(sm_nr))
+                Box::new(ShaderModel50::new(sm_nr, warps_per_sm))
             } else {
                 panic!("Unsupported shader model");
             };
@@ -202,6 +203,11 @@ impl<'a> TestShaderBuilder<'a> {
             num_gprs: 0,
             num_control_barriers: 0,
             num_instrs: 0,
+            num_fma_instrs: 0,
+            num_alu_instrs: 0,
+            num_xu_instrs: 0,
+            num_mem_instrs: 0,
+            num_ctrl_instrs: 0,
             num_static_cycles: 0,
             num_spills_to_mem: 0,
             num_fills_from_mem: 0,
@@ -226,7 +232,7 @@ impl<'a> TestShaderBuilder<'a> {
         s.opt_dce();
         s.legalize();
 
-        s.assign_regs();
+        s.assign_regs(None);
         s.lower_par_copies();
         s.lower_copy_swap();
         s.calc_instr_deps();
@@ -1234,6 +1240,208 @@ fn test_shr64() {
     }
 }
 
+#[test]
+fn test_pack_norm_4x8() {
+    let run = RunSingleton::get();
+    if run.sm.sm() < 70 {
+        return;
+    }
+
+    for signed in [false, true] {
+        let mut b = TestShaderBuilder::new(run.sm.as_ref());
+
+        let mut srcs = [Src::new_zero(); 4];
+        for (c, src) in srcs.iter_mut().enumerate() {
+            let offset = (c * 4).try_into().unwrap();
+            *src = b.ld_test_data(offset, MemType::B32).into();
+        }
+        let dst = b.pack_norm_4x8(srcs, signed);
+        b.st_test_data(16, MemType::B32, dst);
+
+        let bin = b.compile();
+
+        let mut a = Acorn::new();
+        let mut data = Vec::new();
+        for _ in 0..100 {
+            let mut d = [0_u32; 5];
+            for c in 0..4 {
+                let x = a.get_uint(8) as f32 / 255.0;
+                let x = if signed { x * 2.0 - 1.0 } else { x };
+                d[c] = x.to_bits();
+            }
+            data.push(d);
+        }
+        // Out-of-range inputs have to clamp
+        data.push([
+            0.0_f32.to_bits(),
+            1.0_f32.to_bits(),
+            2.0_f32.to_bits(),
+            (-2.0_f32).to_bits(),
+            0,
+        ]);
+
+        run.run.run(&bin, &mut data).unwrap();
+
+        for d in &data {
+            let mut dst = 0_u32;
+            for c in 0..4 {
+                let x = f32::from_bits(d[c]);
+                let byte = if signed {
+                    (x.clamp(-1.0, 1.0) * 127.0).round_ties_even() as i8 as u8
+                } else {
+                    (x.clamp(0.0, 1.0) * 255.0).round_ties_even() as u8
+                };
+                dst |= u32::from(byte) << (c * 8);
+            }
+            assert_eq!(d[4], dst);
+        }
+    }
+}
+
+/// Converts a float which is zero or a normal F16 value to F16
+fn f32_to_f16(x: f32, rnd_mode: FRndMode) -> u16 {
+    let bits = x.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    if bits & 0x7fffffff == 0 {
+        return sign;
+    }
+
+    let exp = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    assert!(exp > 0 && exp < 31, "Not a normal F16 value");
+
+    let mant = bits & 0x7fffff;
+    let mut h = ((exp as u32) << 10) | (mant >> 13);
+    let rem = mant & 0x1fff;
+    if rnd_mode == FRndMode::NearestEven
+        && (rem > 0x1000 || (rem == 0x1000 && (h & 1) != 0))
+    {
+        // A carry out of the mantissa correctly bumps the exponent
+        h += 1;
+    }
+    sign | (h as u16)
+}
+
+#[test]
+fn test_pack_half_2x16() {
+    let run = RunSingleton::get();
+
+    for rnd_mode in [FRndMode::NearestEven, FRndMode::Zero] {
+        let mut b = TestShaderBuilder::new(run.sm.as_ref());
+
+        let x = b.ld_test_data(0, MemType::B32);
+        let y = b.ld_test_data(4, MemType::B32);
+        let dst = b.pack_half_2x16(x.into(), y.into(), rnd_mode);
+        b.st_test_data(8, MemType::B32, dst);
+
+        let bin = b.compile();
+
+        let mut a = Acorn::new();
+        let mut data = Vec::new();
+        for _ in 0..100 {
+            // Stay well within the normal F16 range
+            let x = (a.get_u32() as i32 as f32) / 1_000_000.0;
+            let y = (a.get_u32() as i32 as f32) / 1_000_000.0;
+            data.push([x.to_bits(), y.to_bits(), 0]);
+        }
+        data.push([0.0_f32.to_bits(), (-0.0_f32).to_bits(), 0]);
+        data.push([1.0_f32.to_bits(), (1.0_f32 / 3.0).to_bits(), 0]);
+
+        run.run.run(&bin, &mut data).unwrap();
+
+        for d in &data {
+            let dst = u32::from(f32_to_f16(f32::from_bits(d[0]), rnd_mode))
+                | (u32::from(f32_to_f16(f32::from_bits(d[1]), rnd_mode)) << 16);
+            assert_eq!(d[2], dst);
+        }
+    }
+}
+
+#[test]
+fn test_rotate() {
+    let run = RunSingleton::get();
+    if run.sm.sm() < 70 {
+        return;
+    }
+
+    for right in [false, true] {
+        let mut b = TestShaderBuilder::new(run.sm.as_ref());
+
+        let x = b.ld_test_data(0, MemType::B32);
+        let shift = b.ld_test_data(4, MemType::B32);
+        let dst = b.rotate(x.into(), shift.into(), right);
+        b.st_test_data(8, MemType::B32, dst);
+
+        let bin = b.compile();
+
+        // Shifts of 32 and up wrap, the same as NIR urol/uror
+        let mut a = Acorn::new();
+        let mut data = Vec::new();
+        for shift in [0, 1, 31, 32, 33, 63, 64, 100, 0xffffffff] {
+            data.push([a.get_u32(), shift, 0]);
+        }
+        for _ in 0..100 {
+            data.push([a.get_u32(), a.get_u32(), 0]);
+        }
+
+        run.run.run(&bin, &mut data).unwrap();
+
+        for d in &data {
+            let dst = if right {
+                d[0].rotate_right(d[1] & 0x1f)
+            } else {
+                d[0].rotate_left(d[1] & 0x1f)
+            };
+            assert_eq!(d[2], dst);
+        }
+    }
+}
+
+#[test]
+fn test_rotate64() {
+    let run = RunSingleton::get();
+    if run.sm.sm() < 70 {
+        return;
+    }
+
+    for right in [false, true] {
+        let mut b = TestShaderBuilder::new(run.sm.as_ref());
+
+        let srcs = SSARef::from([
+            b.ld_test_data(0, MemType::B32)[0],
+            b.ld_test_data(4, MemType::B32)[0],
+        ]);
+        let shift = b.ld_test_data(8, MemType::B32);
+        let dst = b.rotate64(srcs.into(), shift.into(), right);
+        b.st_test_data(12, MemType::B32, dst[0].into());
+        b.st_test_data(16, MemType::B32, dst[1].into());
+
+        let bin = b.compile();
+
+        // Shifts of 64 and up wrap, the same as NIR urol/uror
+        let mut a = Acorn::new();
+        let mut data = Vec::new();
+        for shift in [0, 1, 31, 32, 33, 63, 64, 100, 0xffffffff] {
+            data.push([a.get_u32(), a.get_u32(), shift, 0, 0]);
+        }
+        for _ in 0..100 {
+            data.push([a.get_u32(), a.get_u32(), a.get_u32(), 0, 0]);
+        }
+
+        run.run.run(&bin, &mut data).unwrap();
+
+        for d in &data {
+            let src = u64::from(d[0]) | (u64::from(d[1]) << 32);
+            let dst = if right {
+                src.rotate_right(d[2] & 0x3f)
+            } else {
+                src.rotate_left(d[2] & 0x3f)
+            };
+            assert_eq!(d[3], dst as u32);
+            assert_eq!(d[4], (dst >> 32) as u32);
+        }
+    }
+}
+
 #[test]
 fn test_f2fp_pack_ab() {
     let run = RunSingleton::get();
@@ -1300,7 +1508,8 @@ pub fn test_gpr_limit_from_local_size() {
             &mut info.__bindgen_anon_1.cs
         };
         cs_info.local_size = [local_size, 1, 1];
-        let num_gprs = gpr_limit_from_local_size(&cs_info.local_size);
+        let num_gprs =
+            gpr_limit_from_local_size(run.sm.as_ref(), &cs_info.local_size);
         info.num_gprs = num_gprs.try_into().unwrap();
 
         run.run.run::<u8>(&bin, &mut [0; 4096]).unwrap_or_else(|_| {
@@ -1897,6 +1897,11 @@ pub struct LogicOp3 {
 impl LogicOp3 {
     pub const SRC_MASKS: [u8; 3] = [0xf0, 0xcc, 0xaa];
 
+    /// Builds a LUT from an arbitrary function of up to three sources
+    ///
+    /// The function is evaluated once on the per-source bit masks so any
+    /// boolean expression tree written in terms of `&`, `|`, `^`, and `!`
+    /// gets folded into the single LUT immediate used by LOP3 and PLOP3.
     #[inline]
     pub fn new_lut<F: Fn(u8, u8, u8) -> u8>(f: &F) -> LogicOp3 {
         LogicOp3 {
@@ -5813,6 +5818,7 @@ impl DisplayOp for OpNop {
 impl_display_for_op!(OpNop);
 
 #[allow(dead_code)]
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub enum PixVal {
     MsCount,
     CovMask,
@@ -6379,7 +6385,7 @@ impl fmt::Display for OpAnnotate {
     }
 }
 
-#[derive(DisplayOp, DstsAsSlice, SrcsAsSlice, FromVariants)]
+#[derive(DisplayOp, DstsAsSlice, SrcsAsSlice, FromVariants, VariantSizes)]
 pub enum Op {
     FAdd(OpFAdd),
     FFma(OpFFma),
@@ -6506,6 +6512,288 @@ impl Op {
             _ => false,
         }
     }
+
+    /// Returns true if this op is fixed-latency on Volta and later.
+    ///
+    /// This is the common table shared by all shader models.  Anything which
+    /// differs between SMs is handled in `InstrLatencies::needs_scoreboard()`.
+    pub fn has_fixed_latency(&self) -> bool {
+        match self {
+            // Float ALU
+            Op::F2FP(_)
+            | Op::FAdd(_)
+            | Op::FFma(_)
+            | Op::FMnMx(_)
+            | Op::FMul(_)
+            | Op::FSet(_)
+            | Op::FSetP(_)
+            | Op::HAdd2(_)
+            | Op::HFma2(_)
+            | Op::HMul2(_)
+            | Op::HSet2(_)
+            | Op::HSetP2(_)
+            | Op::HMnMx2(_)
+            | Op::FSwzAdd(_) => true,
+
+            // Multi-function unit is variable latency
+            Op::Rro(_) | Op::MuFu(_) => false,
+
+            // Double-precision float ALU
+            Op::DAdd(_)
+            | Op::DFma(_)
+            | Op::DMnMx(_)
+            | Op::DMul(_)
+            | Op::DSetP(_) => false,
+
+            // Integer ALU
+            Op::BRev(_) | Op::Flo(_) | Op::PopC(_) => false,
+            Op::BMsk(_)
+            | Op::IAbs(_)
+            | Op::IAdd2(_)
+            | Op::IAdd2X(_)
+            | Op::IAdd3(_)
+            | Op::IAdd3X(_)
+            | Op::IDp4(_)
+            | Op::IMad(_)
+            | Op::IMad64(_)
+            | Op::IMnMx(_)
+            | Op::IMul(_)
+            | Op::ISetP(_)
+            | Op::Lea(_)
+            | Op::LeaX(_)
+            | Op::Lop2(_)
+            | Op::Lop3(_)
+            | Op::Shf(_)
+            | Op::Shl(_)
+            | Op::Shr(_)
+            | Op::Bfe(_) => true,
+
+            // Conversions are variable latency?!?
+            Op::F2F(_) | Op::F2I(_) | Op::I2F(_) | Op::I2I(_) | Op::FRnd(_) => {
+                false
+            }
+
+            // Move ops
+            Op::Mov(_) | Op::Prmt(_) | Op::Sel(_) => true,
+            Op::Shfl(_) => false,
+
+            // Predicate ops
+            Op::PLop3(_) | Op::PSetP(_) => true,
+
+            // Uniform ops
+            Op::R2UR(_) => false,
+
+            // Texture ops
+            Op::Tex(_)
+            | Op::Tld(_)
+            | Op::Tld4(_)
+            | Op::Tmml(_)
+            | Op::Txd(_)
+            | Op::Txq(_) => false,
+
+            // Surface ops
+            Op::SuLd(_) | Op::SuSt(_) | Op::SuAtom(_) => false,
+
+            // Memory ops
+            Op::Ld(_)
+            | Op::Ldc(_)
+            | Op::St(_)
+            | Op::Atom(_)
+            | Op::AL2P(_)
+            | Op::ALd(_)
+            | Op::ASt(_)
+            | Op::Ipa(_)
+            | Op::CCtl(_)
+            | Op::LdTram(_)
+            | Op::MemBar(_) => false,
+
+            // Control-flow ops
+            Op::BClear(_) | Op::Break(_) | Op::BSSy(_) | Op::BSync(_) => true,
+            Op::SSy(_)
+            | Op::Sync(_)
+            | Op::Brk(_)
+            | Op::PBk(_)
+            | Op::Cont(_)
+            | Op::PCnt(_) => true,
+            Op::Bra(_) | Op::Exit(_) => true,
+            Op::WarpSync(_) => false,
+
+            // The barrier half is HW scoreboarded by the GPR isn't.  When
+            // moving from a GPR to a barrier, we still need a token for WaR
+            // hazards.
+            Op::BMov(_) => false,
+
+            // Geometry ops
+            Op::Out(_) | Op::OutFinal(_) => false,
+
+            // Miscellaneous ops
+            Op::Bar(_)
+            | Op::CS2R(_)
+            | Op::Isberd(_)
+            | Op::Kill(_)
+            | Op::PixLd(_)
+            | Op::S2R(_) => false,
+            Op::Nop(_) | Op::Vote(_) => true,
+
+            // Virtual ops
+            Op::Undef(_)
+            | Op::SrcBar(_)
+            | Op::PhiSrcs(_)
+            | Op::PhiDsts(_)
+            | Op::Copy(_)
+            | Op::Pin(_)
+            | Op::Unpin(_)
+            | Op::Swap(_)
+            | Op::ParCopy(_)
+            | Op::RegOut(_)
+            | Op::Annotate(_) => {
+                panic!("Not a hardware opcode")
+            }
+        }
+    }
+
+    /// Returns the execution unit this op is issued to
+    ///
+    /// This is only a rough grouping intended for statistics.
+    pub fn unit(&self) -> OpUnit {
+        match self {
+            // FMA pipe
+            Op::FAdd(_)
+            | Op::FFma(_)
+            | Op::FMul(_)
+            | Op::FSwzAdd(_)
+            | Op::HAdd2(_)
+            | Op::HFma2(_)
+            | Op::HMul2(_)
+            | Op::IDp4(_)
+            | Op::IMad(_)
+            | Op::IMad64(_)
+            | Op::IMul(_) => OpUnit::Fma,
+
+            // ALU pipe
+            Op::F2FP(_)
+            | Op::FMnMx(_)
+            | Op::FSet(_)
+            | Op::FSetP(_)
+            | Op::HSet2(_)
+            | Op::HSetP2(_)
+            | Op::HMnMx2(_)
+            | Op::BMsk(_)
+            | Op::Bfe(_)
+            | Op::IAbs(_)
+            | Op::IAdd2(_)
+            | Op::IAdd2X(_)
+            | Op::IAdd3(_)
+            | Op::IAdd3X(_)
+            | Op::IMnMx(_)
+            | Op::ISetP(_)
+            | Op::Lea(_)
+            | Op::LeaX(_)
+            | Op::Lop2(_)
+            | Op::Lop3(_)
+            | Op::Shf(_)
+            | Op::Shl(_)
+            | Op::Shr(_)
+            | Op::Mov(_)
+            | Op::Prmt(_)
+            | Op::Sel(_)
+            | Op::PLop3(_)
+            | Op::PSetP(_)
+            | Op::Vote(_) => OpUnit::Alu,
+
+            // Multi-function, double-precision, bit-counting, and conversion
+            // units
+            Op::Rro(_)
+            | Op::MuFu(_)
+            | Op::DAdd(_)
+            | Op::DFma(_)
+            | Op::DMnMx(_)
+            | Op::DMul(_)
+            | Op::DSetP(_)
+            | Op::BRev(_)
+            | Op::Flo(_)
+            | Op::PopC(_)
+            | Op::F2F(_)
+            | Op::F2I(_)
+            | Op::I2F(_)
+            | Op::I2I(_)
+            | Op::FRnd(_) => OpUnit::Xu,
+
+            // Memory, texture, and other ops which go through the memory
+            // pipeline
+            Op::Shfl(_)
+            | Op::R2UR(_)
+            | Op::Tex(_)
+            | Op::Tld(_)
+            | Op::Tld4(_)
+            | Op::Tmml(_)
+            | Op::Txd(_)
+            | Op::Txq(_)
+            | Op::SuLd(_)
+            | Op::SuSt(_)
+            | Op::SuAtom(_)
+            | Op::Ld(_)
+            | Op::Ldc(_)
+            | Op::St(_)
+            | Op::Atom(_)
+            | Op::AL2P(_)
+            | Op::ALd(_)
+            | Op::ASt(_)
+            | Op::Ipa(_)
+            | Op::LdTram(_)
+            | Op::CCtl(_)
+            | Op::MemBar(_)
+            | Op::Out(_)
+            | Op::OutFinal(_)
+            | Op::Isberd(_)
+            | Op::PixLd(_) => OpUnit::Mem,
+
+            // Control flow, barriers, and system values
+            Op::BClear(_)
+            | Op::BMov(_)
+            | Op::Break(_)
+            | Op::BSSy(_)
+            | Op::BSync(_)
+            | Op::Bra(_)
+            | Op::SSy(_)
+            | Op::Sync(_)
+            | Op::Brk(_)
+            | Op::PBk(_)
+            | Op::Cont(_)
+            | Op::PCnt(_)
+            | Op::Exit(_)
+            | Op::WarpSync(_)
+            | Op::Bar(_)
+            | Op::CS2R(_)
+            | Op::Kill(_)
+            | Op::Nop(_)
+            | Op::S2R(_) => OpUnit::Ctrl,
+
+            // Virtual ops
+            Op::Undef(_)
+            | Op::SrcBar(_)
+            | Op::PhiSrcs(_)
+            | Op::PhiDsts(_)
+            | Op::Copy(_)
+            | Op::Pin(_)
+            | Op::Unpin(_)
+            | Op::Swap(_)
+            | Op::ParCopy(_)
+            | Op::RegOut(_)
+            | Op::Annotate(_) => {
+                panic!("Not a hardware opcode")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OpUnit {
+    Fma,
+    Alu,
+    Xu,
+    Mem,
+    Ctrl,
 }
 
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
@@ -6870,140 +7158,6 @@ impl Instr {
         }
     }
 
-    pub fn has_fixed_latency(&self, sm: u8) -> bool {
-        match &self.op {
-            // Float ALU
-            Op::F2FP(_)
-            | Op::FAdd(_)
-            | Op::FFma(_)
-            | Op::FMnMx(_)
-            | Op::FMul(_)
-            | Op::FSet(_)
-            | Op::FSetP(_)
-            | Op::HAdd2(_)
-            | Op::HFma2(_)
-            | Op::HMul2(_)
-            | Op::HSet2(_)
-            | Op::HSetP2(_)
-            | Op::HMnMx2(_)
-            | Op::FSwzAdd(_) => true,
-
-            // Multi-function unit is variable latency
-            Op::Rro(_) | Op::MuFu(_) => false,
-
-            // Double-precision float ALU
-            Op::DAdd(_)
-            | Op::DFma(_)
-            | Op::DMnMx(_)
-            | Op::DMul(_)
-            | Op::DSetP(_) => false,
-
-            // Integer ALU
-            Op::BRev(_) | Op::Flo(_) | Op::PopC(_) => false,
-            Op::IMad(_) | Op::IMul(_) => sm >= 70,
-            Op::BMsk(_)
-            | Op::IAbs(_)
-            | Op::IAdd2(_)
-            | Op::IAdd2X(_)
-            | Op::IAdd3(_)
-            | Op::IAdd3X(_)
-            | Op::IDp4(_)
-            | Op::IMad64(_)
-            | Op::IMnMx(_)
-            | Op::ISetP(_)
-            | Op::Lea(_)
-            | Op::LeaX(_)
-            | Op::Lop2(_)
-            | Op::Lop3(_)
-            | Op::Shf(_)
-            | Op::Shl(_)
-            | Op::Shr(_)
-            | Op::Bfe(_) => true,
-
-            // Conversions are variable latency?!?
-            Op::F2F(_) | Op::F2I(_) | Op::I2F(_) | Op::I2I(_) | Op::FRnd(_) => {
-                false
-            }
-
-            // Move ops
-            Op::Mov(_) | Op::Prmt(_) | Op::Sel(_) => true,
-            Op::Shfl(_) => false,
-
-            // Predicate ops
-            Op::PLop3(_) | Op::PSetP(_) => true,
-
-            // Uniform ops
-            Op::R2UR(_) => false,
-
-            // Texture ops
-            Op::Tex(_)
-            | Op::Tld(_)
-            | Op::Tld4(_)
-            | Op::Tmml(_)
-            | Op::Txd(_)
-            | Op::Txq(_) => false,
-
-            // Surface ops
-            Op::SuLd(_) | Op::SuSt(_) | Op::SuAtom(_) => false,
-
-            // Memory ops
-            Op::Ld(_)
-            | Op::Ldc(_)
-            | Op::St(_)
-            | Op::Atom(_)
-            | Op::AL2P(_)
-            | Op::ALd(_)
-            | Op::ASt(_)
-            | Op::Ipa(_)
-            | Op::CCtl(_)
-            | Op::LdTram(_)
-            | Op::MemBar(_) => false,
-
-            // Control-flow ops
-            Op::BClear(_) | Op::Break(_) | Op::BSSy(_) | Op::BSync(_) => true,
-            Op::SSy(_)
-            | Op::Sync(_)
-            | Op::Brk(_)
-            | Op::PBk(_)
-            | Op::Cont(_)
-            | Op::PCnt(_) => true,
-            Op::Bra(_) | Op::Exit(_) => true,
-            Op::WarpSync(_) => false,
-
-            // The barrier half is HW scoreboarded by the GPR isn't.  When
-            // moving from a GPR to a barrier, we still need a token for WaR
-            // hazards.
-            Op::BMov(_) => false,
-
-            // Geometry ops
-            Op::Out(_) | Op::OutFinal(_) => false,
-
-            // Miscellaneous ops
-            Op::Bar(_)
-            | Op::CS2R(_)
-            | Op::Isberd(_)
-            | Op::Kill(_)
-            | Op::PixLd(_)
-            | Op::S2R(_) => false,
-            Op::Nop(_) | Op::Vote(_) => true,
-
-            // Virtual ops
-            Op::Undef(_)
-            | Op::SrcBar(_)
-            | Op::PhiSrcs(_)
-            | Op::PhiDsts(_)
-            | Op::Copy(_)
-            | Op::Pin(_)
-            | Op::Unpin(_)
-            | Op::Swap(_)
-            | Op::ParCopy(_)
-            | Op::RegOut(_)
-            | Op::Annotate(_) => {
-                panic!("Not a hardware opcode")
-            }
-        }
-    }
-
     pub fn needs_yield(&self) -> bool {
         matches!(&self.op, Op::Bar(_) | Op::BSync(_))
     }
@@ -7177,6 +7331,30 @@ impl Function {
     }
 }
 
+#[cfg(test)]
+impl Function {
+    /// Builds a function out of a straight-line sequence of blocks, each of
+    /// which falls through to the next
+    pub fn new_linear(
+        ssa_alloc: SSAValueAllocator,
+        blocks: Vec<Vec<Box<Instr>>>,
+    ) -> Function {
+        let mut label_alloc = LabelAllocator::new();
+        let num_blocks = blocks.len();
+        let blocks = blocks.into_iter().map(|instrs| BasicBlock {
+            label: label_alloc.alloc(),
+            uniform: true,
+            instrs: instrs,
+        });
+        let edges = (1..num_blocks).map(|i| (i - 1, i));
+        Function {
+            ssa_alloc: ssa_alloc,
+            phi_alloc: PhiAllocator::new(),
+            blocks: CFG::from_blocks_edges(blocks, edges),
+        }
+    }
+}
+
 impl fmt::Display for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut pred_width = 0;
@@ -7456,6 +7634,17 @@ impl FragmentIoInfo {
         }
     }
 
+    /// Marks every generic attribute not set in `attrs_read` as unused.
+    ///
+    /// `attrs_read` is indexed the same as `attr_in`.
+    pub fn retain_attrs_read(&mut self, attrs_read: &[bool; 128]) {
+        for (imap, read) in self.attr_in.iter_mut().zip(attrs_read) {
+            if !read {
+                *imap = PixelImap::Unused;
+            }
+        }
+    }
+
     pub fn mark_barycentric_attr_in(&mut self, addr: u16) {
         assert!(addr >= 0x80 && addr < 0x280);
 
@@ -7479,6 +7668,11 @@ pub struct ShaderInfo {
     pub num_gprs: u8,
     pub num_control_barriers: u8,
     pub num_instrs: u32,
+    pub num_fma_instrs: u32,
+    pub num_alu_instrs: u32,
+    pub num_xu_instrs: u32,
+    pub num_mem_instrs: u32,
+    pub num_ctrl_instrs: u32,
     pub num_static_cycles: u32,
     pub num_spills_to_mem: u32,
     pub num_fills_from_mem: u32,
@@ -7493,48 +7687,193 @@ pub struct ShaderInfo {
     pub io: ShaderIoInfo,
 }
 
+/// Per-SM instruction latency information
+///
+/// All latencies are in cycles.  The read/write index arguments refer to the
+/// source or destination index of the op in question.
+pub trait InstrLatencies {
+    /// Returns true if the op is variable-latency and therefore has to be
+    /// tracked with a scoreboard rather than with delays.
+    fn needs_scoreboard(&self, op: &Op) -> bool;
+
+    /// Number of cycles before the next instruction can issue
+    fn exec(&self, op: &Op) -> u32;
+
+    /// Latency of the given destination when we don't know who reads it
+    fn worst(&self, write: &Op, dst_idx: usize) -> u32;
+
+    /// Read-after-write latency
+    fn raw(&self, write: &Op, dst_idx: usize, read: &Op, src_idx: usize)
+        -> u32;
+
+    /// Write-after-read latency
+    fn war(&self, read: &Op, src_idx: usize, write: &Op, dst_idx: usize)
+        -> u32;
+
+    /// Write-after-write latency
+    fn waw(&self, a: &Op, a_dst_idx: usize, b: &Op, b_dst_idx: usize) -> u32;
+
+    /// Predicate read-after-write latency
+    fn paw(&self, write: &Op, dst_idx: usize) -> u32;
+}
+
+/// Destination latency shared by the Maxwell through Ampere latency tables
+///
+/// Pre-Ampere GPUs run fp64 and fp16 ALU ops on a slower unit and need
+/// `slow_fp64_fp16` set.
+pub fn dst_latency(op: &Op, dst_idx: usize, slow_fp64_fp16: bool) -> u32 {
+    let file = match op.dsts_as_slice()[dst_idx] {
+        Dst::None => return 0,
+        Dst::SSA(vec) => vec.file().unwrap(),
+        Dst::Reg(reg) => reg.file(),
+    };
+
+    let (gpr_latency, pred_latency) = match op {
+        // Double-precision float ALU
+        Op::DAdd(_)
+        | Op::DFma(_)
+        | Op::DMnMx(_)
+        | Op::DMul(_)
+        | Op::DSetP(_)
+        // Half-precision float ALU
+        | Op::HAdd2(_)
+        | Op::HFma2(_)
+        | Op::HMul2(_)
+        | Op::HSet2(_)
+        | Op::HSetP2(_)
+        | Op::HMnMx2(_) if slow_fp64_fp16 => (13, 14),
+        _ => (6, 13)
+    };
+
+    // This is BS and we know it
+    match file {
+        RegFile::GPR => gpr_latency,
+        RegFile::UGPR => 12,
+        RegFile::Pred => pred_latency,
+        RegFile::UPred => 11,
+        RegFile::Bar => 0, // Barriers have a HW scoreboard
+        RegFile::Carry => 6,
+        RegFile::Mem => panic!("Not a register"),
+    }
+}
+
 pub trait ShaderModel {
     fn sm(&self) -> u8;
     fn num_regs(&self, file: RegFile) -> u32;
     fn hw_reserved_gprs(&self) -> u32;
+
+    /// Number of 32-bit registers in the register file of one SM
+    fn reg_file_size(&self) -> u32 {
+        65536
+    }
+
+    /// Maximum number of warps resident on one SM, as reported by the device
+    fn warps_per_sm(&self) -> u32;
+
+    /// GPRs are allocated to warps in multiples of this
+    fn gpr_alloc_granularity(&self) -> u32 {
+        8
+    }
+
+    /// Maximum number of compute thread blocks resident on one SM
+    fn ctas_per_sm(&self) -> u32;
+
+    /// Maximum amount of shared memory on one SM, in bytes
+    fn smem_per_sm(&self) -> u32;
+
     fn crs_size(&self, max_crs_depth: u32) -> u32;
 
+    /// Number of scoreboards available for variable-latency instructions
+    fn num_scoreboards(&self) -> u8;
+
     fn op_can_be_uniform(&self, op: &Op) -> bool;
 
+    fn latencies(&self) -> &dyn InstrLatencies;
+
+    /// Returns the number of cycles `read` has to wait after `write` before
+    /// it can consume any of the SSA values written by `write`, or 0 if it
+    /// doesn't consume any of them.
+    ///
+    /// This is meant for cost models in optimization passes.  For
+    /// variable-latency producers, it only accounts for the fixed part of
+    /// the latency so it should be treated as a lower bound.
+    fn dep_cost(&self, write: &Instr, read: &Instr) -> u32 {
+        let lat = self.latencies();
+        let mut cost = 0;
+        for (d, dst) in write.dsts().iter().enumerate() {
+            let Dst::SSA(dst) = dst else {
+                continue;
+            };
+
+            if let PredRef::SSA(pred) = &read.pred.pred_ref {
+                if dst.iter().any(|ssa| ssa == pred) {
+                    cost = max(cost, lat.paw(&write.op, d));
+                }
+            }
+
+            for (s, src) in read.srcs().iter().enumerate() {
+                let SrcRef::SSA(src) = &src.src_ref else {
+                    continue;
+                };
+                if src.iter().any(|ssa| dst.iter().any(|d| d == ssa)) {
+                    cost = max(cost, lat.raw(&write.op, d, &read.op, s));
+                }
+            }
+        }
+        cost
+    }
+
     fn legalize_op(&self, b: &mut LegalizeBuilder, op: &mut Op);
     fn encode_shader(&self, s: &Shader<'_>) -> Vec<u32>;
 }
 
+fn prev_multiple_of(x: u32, y: u32) -> u32 {
+    (x / y) * y
+}
+
 /// For compute shaders, large values of local_size impose an additional limit
 /// on the number of GPRs per thread
-pub fn gpr_limit_from_local_size(local_size: &[u16; 3]) -> u32 {
-    fn prev_multiple_of(x: u32, y: u32) -> u32 {
-        (x / y) * y
-    }
-
+pub fn gpr_limit_from_local_size(
+    sm: &dyn ShaderModel,
+    local_size: &[u16; 3],
+) -> u32 {
     let local_size = local_size[0] * local_size[1] * local_size[2];
     // Warps are allocated in multiples of 4
     // Multiply that by 32 threads/warp
     let local_size = local_size.next_multiple_of(4 * 32) as u32;
-    let total_regs: u32 = 65536;
 
-    let out = total_regs / local_size;
-    // GPRs are allocated in multiples of 8
-    let out = prev_multiple_of(out, 8);
+    let out = sm.reg_file_size() / local_size;
+    let out = prev_multiple_of(out, sm.gpr_alloc_granularity());
     min(out, 255)
 }
 
-pub fn max_warps_per_sm(gprs: u32) -> u32 {
-    fn prev_multiple_of(x: u32, y: u32) -> u32 {
-        (x / y) * y
+pub fn max_warps_per_sm(sm: &dyn ShaderModel, gprs: u32) -> u32 {
+    // Even a shader which uses no GPRs gets one allocation granule
+    let gprs = max(gprs, 1).next_multiple_of(sm.gpr_alloc_granularity());
+    let max_warps = prev_multiple_of((sm.reg_file_size() / 32) / gprs, 4);
+    min(max_warps, sm.warps_per_sm())
+}
+
+/// Like max_warps_per_sm() but also takes into account that compute shaders
+/// are launched in whole thread blocks which each need their own shared
+/// memory
+pub fn max_warps_per_sm_for_cs(
+    sm: &dyn ShaderModel,
+    gprs: u32,
+    cs_info: &ComputeShaderInfo,
+) -> u32 {
+    let [x, y, z] = cs_info.local_size.map(u32::from);
+    let warps_per_cta = (x * y * z).div_ceil(32);
+
+    let mut ctas = max_warps_per_sm(sm, gprs) / warps_per_cta;
+    ctas = min(ctas, sm.ctas_per_sm());
+    if cs_info.smem_size > 0 {
+        // Shared memory is allocated in multiples of 256B
+        let smem_size = u32::from(cs_info.smem_size).next_multiple_of(0x100);
+        ctas = min(ctas, sm.smem_per_sm() / smem_size);
     }
 
-    // TODO: Take local_size and shared mem limit into account for compute
-    let total_regs: u32 = 65536;
-    // GPRs are allocated in multiples of 8
-    let gprs = gprs.next_multiple_of(8);
-    let max_warps = prev_multiple_of((total_regs / 32) / gprs, 4);
-    min(max_warps, 48)
+    ctas * warps_per_cta
 }
 
 pub struct Shader<'a> {
@@ -7576,12 +7915,18 @@ impl Shader<'_> {
 
     pub fn gather_info(&mut self) {
         let mut num_instrs = 0;
+        let mut num_unit_instrs = [0_u32; 5];
         let mut num_static_cycles = 0;
         let mut uses_global_mem = false;
         let mut writes_global_mem = false;
 
         self.for_each_instr(&mut |instr| {
             num_instrs += 1;
+            // Nops are only there to pad out delays so they don't count
+            // against any unit
+            if !matches!(instr.op, Op::Nop(_)) {
+                num_unit_instrs[instr.op.unit() as usize] += 1;
+            }
             num_static_cycles += instr.deps.delay as u32;
 
             if !uses_global_mem {
@@ -7593,14 +7938,51 @@ impl Shader<'_> {
             }
         });
 
+        if let ShaderIoInfo::Fragment(io) = &mut self.info.io {
+            // Inputs get marked as read when we translate from NIR but the
+            // IPAs reading them may have been optimized away since.  Only
+            // keep the ones which are still read so the header reflects what
+            // the final shader actually consumes.
+            //
+            // This only trims the input map in the SPH.  Attribute addresses
+            // are left alone and nothing is reported back to the driver, so
+            // the previous stage still writes every output it wrote before.
+            let mut attrs_read = [false; 128];
+            for instr in self
+                .functions
+                .iter()
+                .flat_map(|f| &f.blocks)
+                .flat_map(|b| &b.instrs)
+            {
+                let addr = match &instr.op {
+                    Op::Ipa(op) => op.addr,
+                    Op::LdTram(op) => op.addr,
+                    _ => continue,
+                };
+                if addr >= 0x080 && addr < 0x280 {
+                    attrs_read[usize::from(addr - 0x080) / 4] = true;
+                }
+            }
+            io.retain_attrs_read(&attrs_read);
+        }
+
         self.info.num_instrs = num_instrs;
+        self.info.num_fma_instrs = num_unit_instrs[OpUnit::Fma as usize];
+        self.info.num_alu_instrs = num_unit_instrs[OpUnit::Alu as usize];
+        self.info.num_xu_instrs = num_unit_instrs[OpUnit::Xu as usize];
+        self.info.num_mem_instrs = num_unit_instrs[OpUnit::Mem as usize];
+        self.info.num_ctrl_instrs = num_unit_instrs[OpUnit::Ctrl as usize];
         self.info.num_static_cycles = num_static_cycles;
         self.info.uses_global_mem = uses_global_mem;
         self.info.writes_global_mem = writes_global_mem;
 
-        self.info.max_warps_per_sm = max_warps_per_sm(
-            self.info.num_gprs as u32 + self.sm.hw_reserved_gprs(),
-        );
+        let gprs = self.info.num_gprs as u32 + self.sm.hw_reserved_gprs();
+        self.info.max_warps_per_sm = match &self.info.stage {
+            ShaderStageInfo::Compute(cs_info) => {
+                max_warps_per_sm_for_cs(self.sm, gprs, cs_info)
+            }
+            _ => max_warps_per_sm(self.sm, gprs),
+        };
     }
 }
 
@@ -7612,3 +7994,133 @@ impl fmt::Display for Shader<'_> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_op_sizes() {
+        // Every instruction is its own allocation so Op is worth keeping
+        // small.  If an op needs to grow past this, consider boxing its
+        // payload before bumping the limit for all of them.
+        const MAX_OP_SIZE: usize = 200;
+        for (name, size) in Op::variant_sizes() {
+            assert!(*size <= MAX_OP_SIZE, "Op{name} is {size} bytes");
+        }
+
+        // Op should be no bigger than its largest variant plus a tag
+        assert!(std::mem::size_of::<Op>() <= MAX_OP_SIZE + 8);
+        assert!(std::mem::size_of::<Instr>() <= MAX_OP_SIZE + 32);
+    }
+
+    #[test]
+    fn test_dead_attrs_unused() {
+        let sm = crate::sm70::ShaderModel70::new(80, 64);
+        let mut io = FragmentIoInfo {
+            sysvals_in: SysValInfo { ab: 1 << 31, c: 0 },
+            sysvals_in_d: [PixelImap::Unused; 8],
+            attr_in: [PixelImap::Unused; 128],
+            barycentric_attr_in: [0; 4],
+            reads_sample_mask: false,
+            writes_color: 0,
+            writes_sample_mask: false,
+            writes_depth: false,
+        };
+        io.mark_attr_read(0x80, PixelImap::Perspective);
+        io.mark_attr_read(0x84, PixelImap::Perspective);
+
+        // Only the IPA of the first attribute survived optimization
+        let ipa = Instr::new_boxed(OpIpa {
+            dst: RegRef::new(RegFile::GPR, 0, 1).into(),
+            addr: 0x80,
+            freq: InterpFreq::Pass,
+            loc: InterpLoc::Default,
+            inv_w: Src::new_zero(),
+            offset: Src::new_zero(),
+        });
+        let block = BasicBlock {
+            label: LabelAllocator::new().alloc(),
+            uniform: true,
+            instrs: vec![
+                ipa,
+                Instr::new_boxed(OpNop { label: None }),
+                Instr::new_boxed(OpExit {}),
+            ],
+        };
+        let func = Function {
+            ssa_alloc: SSAValueAllocator::new(),
+            phi_alloc: PhiAllocator::new(),
+            blocks: CFG::from_blocks_edges([block], std::iter::empty()),
+        };
+
+        let mut s = Shader {
+            sm: &sm,
+            info: ShaderInfo {
+                max_warps_per_sm: 0,
+                num_gprs: 1,
+                num_control_barriers: 0,
+                num_instrs: 0,
+                num_fma_instrs: 0,
+                num_alu_instrs: 0,
+                num_xu_instrs: 0,
+                num_mem_instrs: 0,
+                num_ctrl_instrs: 0,
+                num_static_cycles: 0,
+                num_spills_to_mem: 0,
+                num_fills_from_mem: 0,
+                num_spills_to_reg: 0,
+                num_fills_from_reg: 0,
+                slm_size: 0,
+                max_crs_depth: 0,
+                uses_global_mem: false,
+                writes_global_mem: false,
+                uses_fp64: false,
+                stage: ShaderStageInfo::Fragment(FragmentShaderInfo {
+                    uses_kill: false,
+                    does_interlock: false,
+                    post_depth_coverage: false,
+                    early_fragment_tests: false,
+                    uses_sample_shading: false,
+                }),
+                io: ShaderIoInfo::Fragment(io),
+            },
+            functions: vec![func],
+        };
+        s.gather_info();
+
+        let ShaderIoInfo::Fragment(io) = &s.info.io else {
+            panic!("Not a fragment shader");
+        };
+        assert_eq!(io.attr_in[0], PixelImap::Perspective);
+        assert_eq!(io.attr_in[1], PixelImap::Unused);
+
+        assert_eq!(s.info.num_instrs, 3);
+        assert_eq!(s.info.num_mem_instrs, 1);
+        assert_eq!(s.info.num_ctrl_instrs, 1);
+    }
+
+    #[test]
+    fn test_max_warps_per_sm_for_cs() {
+        let sm = crate::sm70::ShaderModel70::new(70, 64);
+        let cs_info = |local_size: u16, smem_size: u16| ComputeShaderInfo {
+            local_size: [local_size, 1, 1],
+            smem_size: smem_size,
+        };
+
+        // Without shared memory, only GPRs and the CTA limit matter
+        assert_eq!(max_warps_per_sm_for_cs(&sm, 32, &cs_info(256, 0)), 64);
+        assert_eq!(max_warps_per_sm_for_cs(&sm, 32, &cs_info(32, 0)), 32);
+
+        // A shader with no GPRs is only limited by the hardware
+        assert_eq!(max_warps_per_sm_for_cs(&sm, 0, &cs_info(256, 0)), 64);
+
+        // With 40 GPRs, 48 warps fit but that's only one and a half CTAs
+        assert_eq!(max_warps_per_sm_for_cs(&sm, 40, &cs_info(1024, 0)), 32);
+
+        // 96 KiB of shared memory fits two 48 KiB CTAs
+        let smem_size = 48 << 10;
+        let info = cs_info(128, smem_size);
+        assert_eq!(max_warps_per_sm_for_cs(&sm, 32, &info), 8);
+    }
+}
@@ -0,0 +1,284 @@
+// Copyright © 2024 Collabora, Ltd.
+// SPDX-License-Identifier: MIT
+
+//! Minimal, block-by-block diffs of NAK IR
+//!
+//! Dumping the whole shader after every pass makes it hard to spot what a
+//! single pass actually did.  Instead, we take a textual snapshot of each
+//! block before the pass and diff it against the blocks afterwards.  Blocks
+//! are matched by label and instructions within a block by their text.
+
+use crate::ir::*;
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+struct BlockSnapshot {
+    label: Label,
+    instrs: Vec<String>,
+}
+
+enum Change<'a> {
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The largest LCS table, in entries, diff_lines() is willing to build
+const MAX_LCS_TABLE_SIZE: usize = 1 << 20;
+
+/// Returns the changes which turn `a` into `b`, in order
+fn diff_lines<'a>(a: &'a [String], b: &'a [String]) -> Vec<Change<'a>> {
+    // Most passes only touch a few instructions so strip the common prefix
+    // and suffix before building the quadratic LCS table.
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    // If what's left is still huge, don't bother finding a minimal diff.
+    // Just print the whole thing as removed and re-added.
+    if (a.len() + 1) * (b.len() + 1) > MAX_LCS_TABLE_SIZE {
+        let removed = a.iter().map(|s| Change::Removed(s));
+        let added = b.iter().map(|s| Change::Added(s));
+        return removed.chain(added).collect();
+    }
+
+    // Classic longest common subsequence table, indexed from the end
+    let mut lcs = vec![vec![0_u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if j < b.len()
+            && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j])
+        {
+            changes.push(Change::Added(&b[j]));
+            j += 1;
+        } else {
+            changes.push(Change::Removed(&a[i]));
+            i += 1;
+        }
+    }
+    changes
+}
+
+/// A textual snapshot of a function which can later be diffed against
+pub struct FunctionSnapshot {
+    blocks: Vec<BlockSnapshot>,
+}
+
+impl FunctionSnapshot {
+    pub fn new(func: &Function) -> FunctionSnapshot {
+        let blocks = func
+            .blocks
+            .iter()
+            .map(|b| BlockSnapshot {
+                label: b.label,
+                instrs: b.instrs.iter().map(|i| i.to_string()).collect(),
+            })
+            .collect();
+        FunctionSnapshot { blocks: blocks }
+    }
+
+    /// Writes the differences between `self` and `new`.
+    ///
+    /// Lines are prefixed with `-` for removed instructions, `+` for added
+    /// ones, and `~` for instructions which were removed from one place and
+    /// added in another.  Moved instructions are only printed where they
+    /// ended up.  Returns false if there are no differences.
+    pub fn write_diff(
+        &self,
+        new: &FunctionSnapshot,
+        w: &mut impl Write,
+    ) -> Result<bool, fmt::Error> {
+        const NO_INSTRS: &[String] = &[];
+
+        let old_blocks: HashMap<Label, &BlockSnapshot> =
+            self.blocks.iter().map(|b| (b.label, b)).collect();
+        let new_blocks: HashMap<Label, &BlockSnapshot> =
+            new.blocks.iter().map(|b| (b.label, b)).collect();
+
+        // Diff the blocks in the new order followed by the removed blocks
+        let mut block_diffs = Vec::new();
+        for nb in &new.blocks {
+            let old = old_blocks.get(&nb.label).map(|b| &b.instrs[..]);
+            let changes = diff_lines(old.unwrap_or(NO_INSTRS), &nb.instrs);
+            block_diffs.push((nb.label, old.is_none(), false, changes));
+        }
+        for ob in &self.blocks {
+            if !new_blocks.contains_key(&ob.label) {
+                let changes = diff_lines(&ob.instrs, NO_INSTRS);
+                block_diffs.push((ob.label, false, true, changes));
+            }
+        }
+
+        // Anything which shows up as both removed and added was moved
+        let mut removed: HashMap<&str, u32> = HashMap::new();
+        for (_, _, _, changes) in &block_diffs {
+            for c in changes {
+                if let Change::Removed(s) = c {
+                    *removed.entry(s).or_default() += 1;
+                }
+            }
+        }
+        let mut moved: HashMap<&str, u32> = HashMap::new();
+        for (_, _, _, changes) in &block_diffs {
+            for c in changes {
+                if let Change::Added(s) = c {
+                    if let Some(count) = removed.get_mut(s) {
+                        if *count > 0 {
+                            *count -= 1;
+                            *moved.entry(s).or_default() += 1;
+                        }
+                    }
+                }
+            }
+        }
+        let mut moved_from = moved.clone();
+
+        let mut any_changes = false;
+        for (label, is_new, is_removed, changes) in &block_diffs {
+            let mut lines = Vec::new();
+            for c in changes {
+                match c {
+                    Change::Removed(s) => {
+                        if let Some(count) = moved_from.get_mut(s) {
+                            if *count > 0 {
+                                *count -= 1;
+                                continue;
+                            }
+                        }
+                        lines.push(('-', *s));
+                    }
+                    Change::Added(s) => {
+                        if let Some(count) = moved.get_mut(s) {
+                            if *count > 0 {
+                                *count -= 1;
+                                lines.push(('~', *s));
+                                continue;
+                            }
+                        }
+                        lines.push(('+', *s));
+                    }
+                }
+            }
+
+            if lines.is_empty() && !is_new && !is_removed {
+                continue;
+            }
+            any_changes = true;
+
+            let status = if *is_new {
+                " (added)"
+            } else if *is_removed {
+                " (removed)"
+            } else {
+                ""
+            };
+            writeln!(w, "block {}{}:", label, status)?;
+            for (sym, line) in lines {
+                writeln!(w, "  {} {}", sym, line)?;
+            }
+        }
+
+        Ok(any_changes)
+    }
+}
+
+/// A textual snapshot of a shader which can later be diffed against
+pub struct ShaderSnapshot {
+    functions: Vec<FunctionSnapshot>,
+}
+
+impl ShaderSnapshot {
+    pub fn new(s: &Shader<'_>) -> ShaderSnapshot {
+        ShaderSnapshot {
+            functions: s.functions.iter().map(FunctionSnapshot::new).collect(),
+        }
+    }
+
+    /// Returns the differences between `self` and `new` or None if there
+    /// are none
+    pub fn diff(&self, new: &ShaderSnapshot) -> Option<String> {
+        assert!(self.functions.len() == new.functions.len());
+
+        let mut out = String::new();
+        let mut any_changes = false;
+        for (old_f, new_f) in self.functions.iter().zip(&new.functions) {
+            any_changes |= old_f.write_diff(new_f, &mut out).unwrap();
+        }
+
+        if any_changes {
+            Some(out)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(s: &[&str]) -> Vec<String> {
+        s.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let a = lines(&["a", "b", "c", "d"]);
+        let b = lines(&["a", "c", "e", "d"]);
+        let changes: Vec<_> = diff_lines(&a, &b)
+            .into_iter()
+            .map(|c| match c {
+                Change::Removed(s) => format!("-{s}"),
+                Change::Added(s) => format!("+{s}"),
+            })
+            .collect();
+        assert_eq!(changes, ["-b", "+e"]);
+
+        assert!(diff_lines(&a, &a).is_empty());
+        assert_eq!(diff_lines(&a, &[]).len(), 4);
+        assert_eq!(diff_lines(&[], &b).len(), 4);
+    }
+
+    #[test]
+    fn test_diff_lines_large() {
+        // A single change in a huge block is still found exactly
+        let a: Vec<String> = (0..10000).map(|i| i.to_string()).collect();
+        let mut b = a.clone();
+        b[5000] = "x".to_string();
+        let changes = diff_lines(&a, &b);
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(changes[0], Change::Added("x")));
+        assert!(matches!(changes[1], Change::Removed("5000")));
+
+        // Past the size cap, everything is removed and re-added
+        let b: Vec<String> = a.iter().rev().cloned().collect();
+        let changes = diff_lines(&a, &b);
+        assert_eq!(changes.len(), a.len() + b.len());
+        assert!(changes[..a.len()]
+            .iter()
+            .all(|c| matches!(c, Change::Removed(_))));
+        assert!(changes[a.len()..]
+            .iter()
+            .all(|c| matches!(c, Change::Added(_))));
+    }
+}
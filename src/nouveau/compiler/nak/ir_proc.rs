@@ -88,3 +88,38 @@ pub fn derive_from_variants(input: TokenStream) -> TokenStream {
 
     impls.into()
 }
+
+#[proc_macro_derive(VariantSizes)]
+pub fn derive_variant_sizes(input: TokenStream) -> TokenStream {
+    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+    let enum_type = ident;
+
+    let mut sizes = TokenStream2::new();
+
+    if let Data::Enum(e) = data {
+        for v in e.variants {
+            let var_name = v.ident.to_string();
+            let var_type = match v.fields {
+                Fields::Unnamed(FieldsUnnamed { unnamed, .. }) => unnamed,
+                _ => panic!("Expected Op(OpFoo)"),
+            };
+
+            sizes.extend(quote! {
+                (#var_name, std::mem::size_of::<#var_type>()),
+            });
+        }
+    } else {
+        panic!("Not an enum type");
+    }
+
+    quote! {
+        impl #enum_type {
+            /// Returns the name and size in bytes of each variant's payload
+            #[allow(dead_code)]
+            pub fn variant_sizes() -> &'static [(&'static str, usize)] {
+                &[#sizes]
+            }
+        }
+    }
+    .into()
+}
@@ -8,6 +8,7 @@ mod calc_instr_deps;
 mod const_tracker;
 mod from_nir;
 mod ir;
+mod ir_diff;
 mod legalize;
 mod liveness;
 mod lower_copy_swap;
@@ -15,20 +16,26 @@ mod lower_par_copies;
 mod opt_bar_prop;
 mod opt_copy_prop;
 mod opt_crs;
+mod opt_cvt;
 mod opt_dce;
+mod opt_iadd3;
 mod opt_jump_thread;
 mod opt_lop;
 mod opt_out;
+mod opt_pixld;
 mod opt_prmt;
 mod opt_uniform_instrs;
 mod qmd;
 mod repair_ssa;
 mod sm50;
+mod sm50_instr_latencies;
 mod sm70;
+mod sm70_instr_latencies;
 mod sph;
 mod spill_values;
 mod to_cssa;
 mod union_find;
+mod validate;
 
 #[cfg(test)]
 mod hw_tests;
@@ -7,6 +7,7 @@ use compiler::bitset::BitSet;
 use std::cell::RefCell;
 use std::cmp::{max, Ord, Ordering};
 use std::collections::{hash_set, HashMap, HashSet};
+use std::fmt;
 
 #[derive(Clone)]
 pub struct LiveSet {
@@ -350,6 +351,93 @@ impl SimpleLiveness {
     }
 }
 
+impl SimpleLiveness {
+    /// Writes the live ranges of all SSA values in `file` as ASCII columns
+    /// next to the instructions of `func`
+    ///
+    /// Each live value gets its own column for as long as it is live.  `v`
+    /// marks its definition, `|` the instructions it is live across, and `^`
+    /// its last use.  A value which is defined but never used is marked with
+    /// `x`.  The number of live values is printed before each instruction.
+    pub fn write_live_ranges(
+        &self,
+        func: &Function,
+        file: RegFile,
+        w: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        let mut values: Vec<SSAValue> = self
+            .ssa_block_ip
+            .keys()
+            .filter(|ssa| ssa.file() == file)
+            .copied()
+            .collect();
+        values.sort_by_key(|ssa| ssa.idx());
+
+        for (b_idx, b) in func.blocks.iter().enumerate() {
+            let bl = self.block_live(b_idx);
+            writeln!(w, "block {} {}:", b_idx, b.label)?;
+
+            let mut cols: Vec<Option<SSAValue>> = values
+                .iter()
+                .filter(|ssa| bl.is_live_in(ssa))
+                .map(|ssa| Some(*ssa))
+                .collect();
+
+            let mut lines = Vec::new();
+            for (ip, instr) in b.instrs.iter().enumerate() {
+                let mut defs = Vec::new();
+                instr.for_each_ssa_def(|ssa| {
+                    if ssa.file() == file {
+                        defs.push(*ssa);
+                    }
+                });
+                for ssa in &defs {
+                    if let Some(col) = cols.iter_mut().find(|c| c.is_none()) {
+                        *col = Some(*ssa);
+                    } else {
+                        cols.push(Some(*ssa));
+                    }
+                }
+
+                let mut line = String::new();
+                let mut num_live = 0;
+                for col in cols.iter_mut() {
+                    let Some(ssa) = *col else {
+                        line.push(' ');
+                        continue;
+                    };
+                    num_live += 1;
+
+                    let killed = !bl.is_live_after_ip(&ssa, ip);
+                    let c = match (defs.contains(&ssa), killed) {
+                        (true, true) => 'x',
+                        (true, false) => 'v',
+                        (false, true) => '^',
+                        (false, false) => '|',
+                    };
+                    line.push(c);
+                    if killed {
+                        *col = None;
+                    }
+                }
+
+                while cols.last() == Some(&None) {
+                    cols.pop();
+                }
+
+                lines.push((num_live, line, instr));
+            }
+
+            let width = lines.iter().map(|(_, l, _)| l.len()).max();
+            let width = width.unwrap_or(0);
+            for (num_live, line, instr) in lines {
+                writeln!(w, "{:>4} {:<width$}  {}", num_live, line, instr)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Liveness for SimpleLiveness {
     type PerBlock = SimpleBlockLiveness;
 
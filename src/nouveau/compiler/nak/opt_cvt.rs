@@ -0,0 +1,456 @@
+// Copyright © 2024 Collabora, Ltd.
+// SPDX-License-Identifier: MIT
+
+use crate::ir::*;
+
+use std::collections::HashMap;
+
+/// A round to an integral value without a change of float type
+#[derive(Clone, Copy)]
+struct RoundEntry {
+    src: Src,
+    ty: FloatType,
+    rnd_mode: FRndMode,
+    ftz: bool,
+}
+
+/// A conversion to F64 which is exact so converting back gives the source
+#[derive(Clone, Copy)]
+struct WidenEntry {
+    src: Src,
+    src_type: WidenType,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum WidenType {
+    F32,
+    Int(IntType),
+}
+
+fn ssa_file(src: &Src) -> Option<RegFile> {
+    if !src.src_swizzle.is_none() {
+        return None;
+    }
+    match &src.src_ref {
+        SrcRef::SSA(ssa) => ssa.file(),
+        _ => None,
+    }
+}
+
+fn plain_ssa(src: &Src) -> Option<SSARef> {
+    if !src.src_mod.is_none() {
+        return None;
+    }
+    ssa_file(src)?;
+    src.src_ref.as_ssa().copied()
+}
+
+struct CvtPass {
+    ssa_round: HashMap<SSAValue, RoundEntry>,
+    ssa_widen: HashMap<SSARef, WidenEntry>,
+}
+
+impl CvtPass {
+    fn new() -> CvtPass {
+        CvtPass {
+            ssa_round: HashMap::new(),
+            ssa_widen: HashMap::new(),
+        }
+    }
+
+    fn add_round(&mut self, instr_pred: &Pred, dst: &Dst, entry: RoundEntry) {
+        if entry.ty != FloatType::F32 && entry.ty != FloatType::F64 {
+            return;
+        }
+        if !instr_pred.is_true() || ssa_file(&entry.src).is_none() {
+            return;
+        }
+        if let Dst::SSA(ssa) = dst {
+            self.ssa_round.insert(ssa[0], entry);
+        }
+    }
+
+    fn add_widen(&mut self, instr_pred: &Pred, dst: &Dst, entry: WidenEntry) {
+        if !instr_pred.is_true() || plain_ssa(&entry.src).is_none() {
+            return;
+        }
+        if let Dst::SSA(ssa) = dst {
+            self.ssa_widen.insert(*ssa, entry);
+        }
+    }
+
+    /// Returns a copy to replace a conversion from F64 which undoes an
+    /// exact conversion to F64
+    fn narrow_to_copy(
+        &self,
+        instr_pred: &Pred,
+        dst: Dst,
+        src: &Src,
+        dst_type: WidenType,
+    ) -> Option<Op> {
+        if !instr_pred.is_true() {
+            return None;
+        }
+        let entry = self.ssa_widen.get(&plain_ssa(src)?)?;
+        if entry.src_type != dst_type || ssa_file(&entry.src) != ssa_file(src) {
+            return None;
+        }
+        Some(Op::Copy(OpCopy {
+            dst: dst,
+            src: entry.src,
+        }))
+    }
+
+    fn opt_f2i(&self, op: &mut OpF2I) {
+        // The round has already produced an integral value so the F2I's own
+        // round toward zero doesn't do anything.  As long as both agree on
+        // denorm handling, we can round in the F2I instead.
+        if op.rnd_mode != FRndMode::Zero || !op.src.src_mod.is_none() {
+            return;
+        }
+
+        let Some(file) = ssa_file(&op.src) else {
+            return;
+        };
+        let ssa = op.src.src_ref.as_ssa().unwrap();
+        let Some(entry) = self.ssa_round.get(&ssa[0]) else {
+            return;
+        };
+
+        if entry.ty != op.src_type
+            || entry.ftz != op.ftz
+            || ssa_file(&entry.src) != Some(file)
+        {
+            return;
+        }
+
+        op.src = entry.src;
+        op.rnd_mode = entry.rnd_mode;
+    }
+
+    fn run(&mut self, f: &mut Function) {
+        for b in &mut f.blocks {
+            for instr in &mut b.instrs {
+                let mut copy = None;
+                match &mut instr.op {
+                    // F64 holds any 32-bit integer exactly
+                    Op::I2F(op)
+                        if op.dst_type == FloatType::F64
+                            && op.src_type.bits() == 32 =>
+                    {
+                        let entry = WidenEntry {
+                            src: op.src,
+                            src_type: WidenType::Int(op.src_type),
+                        };
+                        self.add_widen(&instr.pred, &op.dst, entry);
+                    }
+                    Op::F2I(op) => {
+                        if op.src_type == FloatType::F64
+                            && op.dst_type.bits() == 32
+                        {
+                            copy = self.narrow_to_copy(
+                                &instr.pred,
+                                op.dst,
+                                &op.src,
+                                WidenType::Int(op.dst_type),
+                            );
+                        }
+                        if copy.is_none() {
+                            self.opt_f2i(op);
+                        }
+                    }
+                    // Without FTZ, F32 to F64 and back only quiets signaling
+                    // NaNs, which Vulkan doesn't guarantee to preserve
+                    Op::F2F(op)
+                        if op.src_type == FloatType::F32
+                            && op.dst_type == FloatType::F64
+                            && !op.ftz =>
+                    {
+                        let entry = WidenEntry {
+                            src: op.src,
+                            src_type: WidenType::F32,
+                        };
+                        self.add_widen(&instr.pred, &op.dst, entry);
+                    }
+                    Op::F2F(op)
+                        if op.src_type == FloatType::F64
+                            && op.dst_type == FloatType::F32
+                            && !op.ftz
+                            && !op.integer_rnd =>
+                    {
+                        copy = self.narrow_to_copy(
+                            &instr.pred,
+                            op.dst,
+                            &op.src,
+                            WidenType::F32,
+                        );
+                    }
+                    // SM50 rounds to integral with F2F
+                    Op::F2F(op)
+                        if op.integer_rnd
+                            && !op.high
+                            && op.src_type == op.dst_type =>
+                    {
+                        let entry = RoundEntry {
+                            src: op.src,
+                            ty: op.src_type,
+                            rnd_mode: op.rnd_mode,
+                            ftz: op.ftz,
+                        };
+                        self.add_round(&instr.pred, &op.dst, entry);
+                    }
+                    Op::FRnd(op) if op.src_type == op.dst_type => {
+                        let entry = RoundEntry {
+                            src: op.src,
+                            ty: op.src_type,
+                            rnd_mode: op.rnd_mode,
+                            ftz: op.ftz,
+                        };
+                        self.add_round(&instr.pred, &op.dst, entry);
+                    }
+                    _ => (),
+                }
+                if let Some(copy) = copy {
+                    instr.op = copy;
+                }
+            }
+        }
+    }
+}
+
+impl Shader<'_> {
+    /// Cleans up chains of conversions
+    ///
+    /// Rounds to integral values are folded into the F2I consuming them.
+    /// Conversions to F64 and back which are exact, F32 or 32-bit integers
+    /// through F64, are replaced with copies.  The folded conversions are left
+    /// for DCE to clean up.
+    ///
+    /// None of the conversion ops have a saturate modifier.  from_nir already
+    /// folds fsat into the FADD, FMUL, or FFMA producing the value so there is
+    /// nothing left to merge there.
+    pub fn opt_cvt(&mut self) {
+        for f in &mut self.functions {
+            CvtPass::new().run(f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frnd(dst: SSAValue, src: SSAValue, rnd_mode: FRndMode) -> OpFRnd {
+        OpFRnd {
+            dst: dst.into(),
+            src: src.into(),
+            dst_type: FloatType::F32,
+            src_type: FloatType::F32,
+            rnd_mode: rnd_mode,
+            ftz: false,
+        }
+    }
+
+    fn f2i(dst: SSAValue, src: SSAValue) -> OpF2I {
+        OpF2I {
+            dst: dst.into(),
+            src: src.into(),
+            src_type: FloatType::F32,
+            dst_type: IntType::I32,
+            rnd_mode: FRndMode::Zero,
+            ftz: false,
+        }
+    }
+
+    fn f2f(dst: SSARef, src: SSARef, src_type: FloatType) -> OpF2F {
+        let dst_type = match src_type {
+            FloatType::F32 => FloatType::F64,
+            _ => FloatType::F32,
+        };
+        OpF2F {
+            dst: dst.into(),
+            src: src.into(),
+            src_type: src_type,
+            dst_type: dst_type,
+            rnd_mode: FRndMode::NearestEven,
+            ftz: false,
+            high: false,
+            integer_rnd: false,
+        }
+    }
+
+    fn i2f64(dst: SSARef, src: SSAValue, src_type: IntType) -> OpI2F {
+        OpI2F {
+            dst: dst.into(),
+            src: src.into(),
+            dst_type: FloatType::F64,
+            src_type: src_type,
+            rnd_mode: FRndMode::NearestEven,
+        }
+    }
+
+    fn f64_to_i(dst: SSAValue, src: SSARef, dst_type: IntType) -> OpF2I {
+        OpF2I {
+            dst: dst.into(),
+            src: src.into(),
+            src_type: FloatType::F64,
+            dst_type: dst_type,
+            rnd_mode: FRndMode::Zero,
+            ftz: false,
+        }
+    }
+
+    fn run(ssa_alloc: SSAValueAllocator, instrs: Vec<Box<Instr>>) -> Function {
+        let mut f = Function::new_linear(ssa_alloc, vec![instrs]);
+        CvtPass::new().run(&mut f);
+        f
+    }
+
+    fn f2i_src_rnd(f: &Function) -> (Src, FRndMode) {
+        let Op::F2I(op) = &f.blocks[0].instrs[1].op else {
+            panic!("Expected an F2I");
+        };
+        (op.src, op.rnd_mode)
+    }
+
+    fn copy_src(f: &Function) -> Option<Src> {
+        match &f.blocks[0].instrs[1].op {
+            Op::Copy(op) => Some(op.src),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_fold_frnd() {
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, r, i] = [(); 3].map(|_| ssa_alloc.alloc(RegFile::GPR));
+        let f = run(
+            ssa_alloc,
+            vec![
+                Instr::new_boxed(frnd(r, x, FRndMode::NegInf)),
+                Instr::new_boxed(f2i(i, r)),
+            ],
+        );
+        assert!(f2i_src_rnd(&f) == (x.into(), FRndMode::NegInf));
+    }
+
+    #[test]
+    fn test_fold_f2f_rnd() {
+        // SM50 rounds to integral with F2F
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, r, i] = [(); 3].map(|_| ssa_alloc.alloc(RegFile::GPR));
+        let mut rnd = f2f(r.into(), x.into(), FloatType::F32);
+        rnd.dst_type = FloatType::F32;
+        rnd.rnd_mode = FRndMode::PosInf;
+        rnd.integer_rnd = true;
+        let f = run(
+            ssa_alloc,
+            vec![Instr::new_boxed(rnd), Instr::new_boxed(f2i(i, r))],
+        );
+        assert!(f2i_src_rnd(&f) == (x.into(), FRndMode::PosInf));
+    }
+
+    #[test]
+    fn test_no_fold_rnd_mode() {
+        // An F2I which doesn't truncate already rounds on its own
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, r, i] = [(); 3].map(|_| ssa_alloc.alloc(RegFile::GPR));
+        let mut cvt = f2i(i, r);
+        cvt.rnd_mode = FRndMode::NearestEven;
+        let f = run(
+            ssa_alloc,
+            vec![
+                Instr::new_boxed(frnd(r, x, FRndMode::NegInf)),
+                Instr::new_boxed(cvt),
+            ],
+        );
+        assert!(f2i_src_rnd(&f) == (r.into(), FRndMode::NearestEven));
+    }
+
+    #[test]
+    fn test_no_fold_ftz() {
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, r, i] = [(); 3].map(|_| ssa_alloc.alloc(RegFile::GPR));
+        let mut rnd = frnd(r, x, FRndMode::NegInf);
+        rnd.ftz = true;
+        let f = run(
+            ssa_alloc,
+            vec![Instr::new_boxed(rnd), Instr::new_boxed(f2i(i, r))],
+        );
+        assert!(f2i_src_rnd(&f) == (r.into(), FRndMode::Zero));
+    }
+
+    #[test]
+    fn test_no_fold_predicated() {
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, r, i] = [(); 3].map(|_| ssa_alloc.alloc(RegFile::GPR));
+        let mut rnd = Instr::new_boxed(frnd(r, x, FRndMode::NegInf));
+        rnd.pred = ssa_alloc.alloc(RegFile::Pred).into();
+        let f = run(ssa_alloc, vec![rnd, Instr::new_boxed(f2i(i, r))]);
+        assert!(f2i_src_rnd(&f) == (r.into(), FRndMode::Zero));
+    }
+
+    #[test]
+    fn test_f32_round_trip() {
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, y] = [(); 2].map(|_| ssa_alloc.alloc_vec(RegFile::GPR, 1));
+        let d = ssa_alloc.alloc_vec(RegFile::GPR, 2);
+        let f = run(
+            ssa_alloc,
+            vec![
+                Instr::new_boxed(f2f(d, x, FloatType::F32)),
+                Instr::new_boxed(f2f(y, d, FloatType::F64)),
+            ],
+        );
+        assert!(copy_src(&f) == Some(x.into()));
+    }
+
+    #[test]
+    fn test_no_f32_round_trip_ftz() {
+        // FTZ on the way down flushes F32 denorms which are normal in F64
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, y] = [(); 2].map(|_| ssa_alloc.alloc_vec(RegFile::GPR, 1));
+        let d = ssa_alloc.alloc_vec(RegFile::GPR, 2);
+        let mut narrow = f2f(y, d, FloatType::F64);
+        narrow.ftz = true;
+        let f = run(
+            ssa_alloc,
+            vec![
+                Instr::new_boxed(f2f(d, x, FloatType::F32)),
+                Instr::new_boxed(narrow),
+            ],
+        );
+        assert!(copy_src(&f).is_none());
+    }
+
+    #[test]
+    fn test_int_round_trip() {
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, y] = [(); 2].map(|_| ssa_alloc.alloc(RegFile::GPR));
+        let d = ssa_alloc.alloc_vec(RegFile::GPR, 2);
+        let f = run(
+            ssa_alloc,
+            vec![
+                Instr::new_boxed(i2f64(d, x, IntType::U32)),
+                Instr::new_boxed(f64_to_i(y, d, IntType::U32)),
+            ],
+        );
+        assert!(copy_src(&f) == Some(x.into()));
+    }
+
+    #[test]
+    fn test_no_int_round_trip_signedness() {
+        // Converting back with a different signedness clamps differently
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, y] = [(); 2].map(|_| ssa_alloc.alloc(RegFile::GPR));
+        let d = ssa_alloc.alloc_vec(RegFile::GPR, 2);
+        let f = run(
+            ssa_alloc,
+            vec![
+                Instr::new_boxed(i2f64(d, x, IntType::U32)),
+                Instr::new_boxed(f64_to_i(y, d, IntType::I32)),
+            ],
+        );
+        assert!(copy_src(&f).is_none());
+    }
+}
@@ -0,0 +1,280 @@
+// Copyright © 2024 Collabora, Ltd.
+// SPDX-License-Identifier: MIT
+
+use crate::ir::*;
+
+use std::collections::HashMap;
+
+struct IAdd3Pass {
+    use_counts: HashMap<SSAValue, u32>,
+    ssa_add: HashMap<SSAValue, [Src; 3]>,
+}
+
+fn src_is_const(src: &Src) -> bool {
+    matches!(src.src_ref, SrcRef::Imm32(_) | SrcRef::CBuf(_))
+}
+
+impl IAdd3Pass {
+    fn new(f: &Function) -> IAdd3Pass {
+        let mut use_counts = HashMap::new();
+        for b in &f.blocks {
+            for instr in &b.instrs {
+                if let PredRef::SSA(ssa) = instr.pred.pred_ref {
+                    use_counts.entry(ssa).and_modify(|e| *e += 1).or_insert(1);
+                }
+
+                for src in instr.srcs() {
+                    if let SrcRef::SSA(vec) = src.src_ref {
+                        for ssa in vec.iter() {
+                            use_counts
+                                .entry(*ssa)
+                                .and_modify(|e| *e += 1)
+                                .or_insert(1);
+                        }
+                    }
+                }
+            }
+        }
+        IAdd3Pass {
+            use_counts: use_counts,
+            ssa_add: HashMap::new(),
+        }
+    }
+
+    /// Tries to replace srcs[src_idx] with the sources of the add which
+    /// defines it.  Returns true if progress was made.
+    ///
+    /// Because the overflow predicates of neither add are used, this only
+    /// relies on 32-bit addition being associative.  A negate modifier on
+    /// the propagated source distributes over the sources of the add.
+    fn try_prop_to_src(
+        &self,
+        dst_file: RegFile,
+        srcs: &mut [Src; 3],
+        src_idx: usize,
+    ) -> bool {
+        let SrcRef::SSA(vec) = srcs[src_idx].src_ref else {
+            return false;
+        };
+        if vec.comps() != 1 || vec[0].file() != dst_file {
+            return false;
+        }
+        let ssa = vec[0];
+
+        let Some(entry) = self.ssa_add.get(&ssa) else {
+            return false;
+        };
+
+        // If the add has other uses, folding it would only duplicate it.
+        if *self.use_counts.get(&ssa).unwrap() > 1 {
+            return false;
+        }
+
+        let negate = srcs[src_idx].src_mod.is_ineg();
+        let mut new_srcs = *srcs;
+        new_srcs[src_idx] = SrcRef::Zero.into();
+
+        for esrc in entry.iter() {
+            if esrc.is_zero() {
+                continue;
+            }
+
+            let Some(slot) = new_srcs.iter().position(|s| s.is_zero()) else {
+                return false;
+            };
+            new_srcs[slot] = if negate { esrc.ineg() } else { *esrc };
+        }
+
+        // The hardware only takes one immediate or cbuf source.  Folding
+        // two of them together would just trade the add for a copy.
+        if new_srcs.iter().filter(|s| src_is_const(s)).count() > 1 {
+            return false;
+        }
+
+        *srcs = new_srcs;
+        true
+    }
+
+    fn opt_iadd3(&mut self, instr: &mut Instr) {
+        let Op::IAdd3(op) = &mut instr.op else {
+            return;
+        };
+
+        if !op.overflow[0].is_none() || !op.overflow[1].is_none() {
+            return;
+        }
+
+        let Dst::SSA(dst) = op.dst else {
+            return;
+        };
+        assert!(dst.comps() == 1);
+        let dst = dst[0];
+
+        loop {
+            let mut progress = false;
+            for i in 0..3 {
+                if self.try_prop_to_src(dst.file(), &mut op.srcs, i) {
+                    progress = true;
+                }
+            }
+            if !progress {
+                break;
+            }
+        }
+
+        // Only unpredicated adds can be folded into their users
+        if instr.pred.is_true() {
+            self.ssa_add.insert(dst, op.srcs);
+        }
+    }
+
+    fn run(&mut self, f: &mut Function) {
+        for b in &mut f.blocks {
+            for instr in &mut b.instrs {
+                self.opt_iadd3(instr);
+            }
+        }
+    }
+}
+
+impl Shader<'_> {
+    /// Combines chains of two-source adds into three-source IADD3s
+    ///
+    /// Adds which write a carry or overflow predicate are left alone since
+    /// re-associating them would change the predicate value.  In particular,
+    /// this means 64-bit IADD3 + IADD3.X pairs are never combined.  Folding
+    /// two of them would need both carry-outs of the low IADD3 and that is
+    /// rarely a win over the two pairs.  This leaves the folded adds dead so
+    /// it should be followed by DCE.
+    pub fn opt_iadd3(&mut self) {
+        for f in &mut self.functions {
+            let mut pass = IAdd3Pass::new(f);
+            pass.run(f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn iadd3(dst: SSAValue, srcs: [Src; 3]) -> Box<Instr> {
+        Instr::new_boxed(OpIAdd3 {
+            dst: dst.into(),
+            overflow: [Dst::None; 2],
+            srcs: srcs,
+        })
+    }
+
+    fn iadd3_srcs(f: &Function, ip: usize) -> [Src; 3] {
+        let Op::IAdd3(op) = &f.blocks[0].instrs[ip].op else {
+            panic!("Expected an IADD3");
+        };
+        op.srcs
+    }
+
+    #[test]
+    fn test_fold() {
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, y, z, a, b] = [(); 5].map(|_| ssa_alloc.alloc(RegFile::GPR));
+        let zero = Src::new_zero();
+        let mut f = Function::new_linear(
+            ssa_alloc,
+            vec![vec![
+                iadd3(a, [x.into(), y.into(), zero]),
+                iadd3(b, [a.into(), z.into(), zero]),
+            ]],
+        );
+        IAdd3Pass::new(&f).run(&mut f);
+
+        assert!(iadd3_srcs(&f, 1) == [x.into(), z.into(), y.into()]);
+    }
+
+    #[test]
+    fn test_fold_neg() {
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, y, z, a, b] = [(); 5].map(|_| ssa_alloc.alloc(RegFile::GPR));
+        let zero = Src::new_zero();
+        let mut f = Function::new_linear(
+            ssa_alloc,
+            vec![vec![
+                iadd3(a, [x.into(), y.into(), zero]),
+                iadd3(b, [Src::from(a).ineg(), z.into(), zero]),
+            ]],
+        );
+        IAdd3Pass::new(&f).run(&mut f);
+
+        let srcs = iadd3_srcs(&f, 1);
+        assert!(srcs == [Src::from(x).ineg(), z.into(), Src::from(y).ineg()]);
+    }
+
+    #[test]
+    fn test_no_fold_multi_use() {
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, y, z, a, b, c] = [(); 6].map(|_| ssa_alloc.alloc(RegFile::GPR));
+        let zero = Src::new_zero();
+        let mut f = Function::new_linear(
+            ssa_alloc,
+            vec![vec![
+                iadd3(a, [x.into(), y.into(), zero]),
+                iadd3(b, [a.into(), z.into(), zero]),
+                iadd3(c, [a.into(), z.into(), zero]),
+            ]],
+        );
+        IAdd3Pass::new(&f).run(&mut f);
+
+        assert!(iadd3_srcs(&f, 1) == [a.into(), z.into(), zero]);
+    }
+
+    #[test]
+    fn test_no_fold_predicated() {
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, y, z, a, b] = [(); 5].map(|_| ssa_alloc.alloc(RegFile::GPR));
+        let p = ssa_alloc.alloc(RegFile::Pred);
+        let zero = Src::new_zero();
+        let mut add_a = iadd3(a, [x.into(), y.into(), zero]);
+        add_a.pred = p.into();
+        let mut f = Function::new_linear(
+            ssa_alloc,
+            vec![vec![add_a, iadd3(b, [a.into(), z.into(), zero])]],
+        );
+        IAdd3Pass::new(&f).run(&mut f);
+
+        assert!(iadd3_srcs(&f, 1) == [a.into(), z.into(), zero]);
+    }
+
+    #[test]
+    fn test_no_fold_carry_chain() {
+        // a = x + y and b = a + z as 64-bit IADD3 + IADD3.X pairs
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let [x, y, z, a, b] =
+            [(); 5].map(|_| ssa_alloc.alloc_vec(RegFile::GPR, 2));
+        let [ca, cb] = [(); 2].map(|_| ssa_alloc.alloc(RegFile::Pred));
+        let zero = Src::new_zero();
+        let add64 = |dst: SSARef, x: SSARef, y: SSARef, carry: SSAValue| {
+            [
+                Instr::new_boxed(OpIAdd3 {
+                    dst: dst[0].into(),
+                    overflow: [carry.into(), Dst::None],
+                    srcs: [x[0].into(), y[0].into(), zero],
+                }),
+                Instr::new_boxed(OpIAdd3X {
+                    dst: dst[1].into(),
+                    overflow: [Dst::None; 2],
+                    srcs: [x[1].into(), y[1].into(), zero],
+                    carry: [carry.into(), SrcRef::False.into()],
+                }),
+            ]
+        };
+        let instrs = add64(a, x, y, ca).into_iter().chain(add64(b, a, z, cb));
+        let mut f = Function::new_linear(ssa_alloc, vec![instrs.collect()]);
+        IAdd3Pass::new(&f).run(&mut f);
+
+        assert!(iadd3_srcs(&f, 2) == [a[0].into(), z[0].into(), zero]);
+        let Op::IAdd3X(hi) = &f.blocks[0].instrs[3].op else {
+            panic!("Expected an IADD3.X");
+        };
+        assert!(hi.srcs == [a[1].into(), z[1].into(), zero]);
+        assert!(hi.carry == [cb.into(), SrcRef::False.into()]);
+    }
+}
@@ -267,6 +267,12 @@ impl LopPass {
 }
 
 impl Shader<'_> {
+    /// Collapses trees of LOP3 and PLOP3 ops into single instructions
+    ///
+    /// Whenever a source is itself produced by a logic op, its sources are
+    /// pulled into any free source slots and the two LUTs are composed with
+    /// LogicOp3::new_lut().  This works for any tree with at most three
+    /// distinct leaves so there is no need for pattern-specific combines.
     pub fn opt_lop(&mut self) {
         for f in &mut self.functions {
             let mut pass = LopPass::new(f);
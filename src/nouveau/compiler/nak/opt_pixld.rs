@@ -0,0 +1,167 @@
+// Copyright © 2024 Collabora, Ltd.
+// SPDX-License-Identifier: MIT
+
+use crate::ir::*;
+
+use std::collections::HashMap;
+
+fn pixld_val(instr: &Instr) -> Option<PixVal> {
+    match &instr.op {
+        Op::PixLd(op) if instr.pred.is_true() => {
+            if let Dst::SSA(ssa) = &op.dst {
+                if ssa.comps() == 1 {
+                    return Some(op.val);
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+fn opt_pixld_func(f: &mut Function) {
+    let mut counts: HashMap<PixVal, u32> = HashMap::new();
+    for b in &f.blocks {
+        for instr in &b.instrs {
+            if let Some(val) = pixld_val(instr) {
+                *counts.entry(val).or_default() += 1;
+            }
+        }
+    }
+
+    // A single PIXLD is left where it is.  Hoisting it would only make its
+    // result live for longer.
+    counts.retain(|_, count| *count > 1);
+    if counts.is_empty() {
+        return;
+    }
+
+    let mut hoisted: HashMap<PixVal, SSAValue> = HashMap::new();
+    for b in &mut f.blocks {
+        for instr in &mut b.instrs {
+            let Some(val) = pixld_val(instr) else {
+                continue;
+            };
+            if !counts.contains_key(&val) {
+                continue;
+            }
+
+            let ssa = *hoisted
+                .entry(val)
+                .or_insert_with(|| f.ssa_alloc.alloc(RegFile::GPR));
+
+            let Op::PixLd(op) = &instr.op else {
+                panic!("Not a PIXLD");
+            };
+            instr.op = Op::Copy(OpCopy {
+                dst: op.dst,
+                src: ssa.into(),
+            });
+        }
+    }
+
+    let mut pixlds: Vec<_> = hoisted.into_iter().collect();
+    pixlds.sort_by_key(|(_, ssa)| ssa.idx());
+
+    let entry = &mut f.blocks[0];
+    let hoisted_instrs = pixlds.into_iter().map(|(val, ssa)| {
+        Instr::new_boxed(OpPixLd {
+            dst: ssa.into(),
+            val,
+        })
+    });
+    entry.instrs.splice(0..0, hoisted_instrs);
+}
+
+impl Shader<'_> {
+    /// Combines repeated PIXLD queries of the same value into a single PIXLD
+    /// at the top of the shader
+    ///
+    /// Every PixVal is fixed for the lifetime of the invocation and PIXLD is
+    /// decoupled, so issuing it early costs nothing.  The old PIXLDs are
+    /// replaced with copies, so this should be followed by copy propagation.
+    pub fn opt_pixld(&mut self) {
+        if !matches!(self.info.stage, ShaderStageInfo::Fragment(_)) {
+            return;
+        }
+
+        for f in &mut self.functions {
+            opt_pixld_func(f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixld(dst: SSAValue, val: PixVal) -> Box<Instr> {
+        Instr::new_boxed(OpPixLd {
+            dst: dst.into(),
+            val,
+        })
+    }
+
+    fn pixld_count(f: &Function) -> usize {
+        f.blocks
+            .iter()
+            .flat_map(|b| &b.instrs)
+            .filter(|i| matches!(i.op, Op::PixLd(_)))
+            .count()
+    }
+
+    #[test]
+    fn test_combine_across_blocks() {
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let a = ssa_alloc.alloc(RegFile::GPR);
+        let b = ssa_alloc.alloc(RegFile::GPR);
+        let mut f = Function::new_linear(
+            ssa_alloc,
+            vec![
+                vec![pixld(a, PixVal::CovMask)],
+                vec![pixld(b, PixVal::CovMask), Instr::new_boxed(OpExit {})],
+            ],
+        );
+        opt_pixld_func(&mut f);
+
+        assert_eq!(pixld_count(&f), 1);
+        let Op::PixLd(hoisted) = &f.blocks[0].instrs[0].op else {
+            panic!("Expected a PIXLD at the top of block 0");
+        };
+        assert!(hoisted.val == PixVal::CovMask);
+        let hoisted_src = Src::from(hoisted.dst.as_ssa().unwrap()[0]);
+
+        for (block, dst) in [(0, a), (1, b)] {
+            let copy = f.blocks[block]
+                .instrs
+                .iter()
+                .find_map(|i| match &i.op {
+                    Op::Copy(op) if op.dst.as_ssa().unwrap()[0] == dst => {
+                        Some(op)
+                    }
+                    _ => None,
+                })
+                .expect("PIXLD was not replaced with a copy");
+            assert!(copy.src == hoisted_src);
+        }
+    }
+
+    #[test]
+    fn test_single_pixld_not_moved() {
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let a = ssa_alloc.alloc(RegFile::GPR);
+        let b = ssa_alloc.alloc(RegFile::GPR);
+        let mut f = Function::new_linear(
+            ssa_alloc,
+            vec![
+                vec![pixld(a, PixVal::CovMask)],
+                vec![pixld(b, PixVal::MyIndex), Instr::new_boxed(OpExit {})],
+            ],
+        );
+        opt_pixld_func(&mut f);
+
+        assert_eq!(pixld_count(&f), 2);
+        assert_eq!(f.blocks[0].instrs.len(), 1);
+        assert!(matches!(f.blocks[1].instrs[0].op, Op::PixLd(_)));
+    }
+}
@@ -5,6 +5,7 @@ use crate::ir::*;
 use crate::legalize::{
     src_is_reg, swap_srcs_if_not_reg, LegalizeBuildHelpers, LegalizeBuilder,
 };
+use crate::sm50_instr_latencies::SM50InstrLatencies;
 use bitview::*;
 
 use std::collections::HashMap;
@@ -12,12 +13,18 @@ use std::ops::Range;
 
 pub struct ShaderModel50 {
     sm: u8,
+    warps_per_sm: u32,
+    latencies: SM50InstrLatencies,
 }
 
 impl ShaderModel50 {
-    pub fn new(sm: u8) -> Self {
+    pub fn new(sm: u8, warps_per_sm: u32) -> Self {
         assert!(sm >= 50 && sm < 70);
-        Self { sm }
+        Self {
+            sm,
+            warps_per_sm,
+            latencies: SM50InstrLatencies::new(sm),
+        }
     }
 }
 
@@ -42,6 +49,21 @@ impl ShaderModel for ShaderModel50 {
         0
     }
 
+    fn warps_per_sm(&self) -> u32 {
+        self.warps_per_sm
+    }
+
+    fn ctas_per_sm(&self) -> u32 {
+        32
+    }
+
+    fn smem_per_sm(&self) -> u32 {
+        match self.sm {
+            52 | 61 => 96 << 10,
+            _ => 64 << 10,
+        }
+    }
+
     fn crs_size(&self, max_crs_depth: u32) -> u32 {
         if max_crs_depth <= 16 {
             0
@@ -52,10 +74,18 @@ impl ShaderModel for ShaderModel50 {
         }
     }
 
+    fn num_scoreboards(&self) -> u8 {
+        6
+    }
+
     fn op_can_be_uniform(&self, _op: &Op) -> bool {
         false
     }
 
+    fn latencies(&self) -> &dyn InstrLatencies {
+        &self.latencies
+    }
+
     fn legalize_op(&self, b: &mut LegalizeBuilder, op: &mut Op) {
         as_sm50_op_mut(op).legalize(b);
     }
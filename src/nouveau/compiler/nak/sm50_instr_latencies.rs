@@ -0,0 +1,84 @@
+// Copyright © 2026 Collabora, Ltd.
+// SPDX-License-Identifier: MIT
+
+use crate::ir::*;
+
+/// Instruction latencies for Maxwell and Pascal
+pub struct SM50InstrLatencies {}
+
+impl SM50InstrLatencies {
+    pub fn new(sm: u8) -> Self {
+        assert!(sm >= 50 && sm < 70);
+        Self {}
+    }
+
+    fn dst_latency(&self, op: &Op, dst_idx: usize) -> u32 {
+        dst_latency(op, dst_idx, true)
+    }
+}
+
+impl InstrLatencies for SM50InstrLatencies {
+    fn needs_scoreboard(&self, op: &Op) -> bool {
+        // Maxwell and Pascal run integer multiplies through a variable-latency
+        // unit so those need to be scoreboarded on top of the common set.
+        match op {
+            Op::IMad(_) | Op::IMul(_) => true,
+            _ => !op.has_fixed_latency(),
+        }
+    }
+
+    fn exec(&self, op: &Op) -> u32 {
+        match op {
+            Op::CCtl(_)
+            | Op::MemBar(_)
+            | Op::Bra(_)
+            | Op::SSy(_)
+            | Op::Sync(_)
+            | Op::Brk(_)
+            | Op::PBk(_)
+            | Op::Cont(_)
+            | Op::PCnt(_)
+            | Op::Exit(_)
+            | Op::Bar(_)
+            | Op::Kill(_)
+            | Op::OutFinal(_) => 13,
+            _ => 1,
+        }
+    }
+
+    fn worst(&self, write: &Op, dst_idx: usize) -> u32 {
+        self.dst_latency(write, dst_idx)
+    }
+
+    fn raw(
+        &self,
+        write: &Op,
+        dst_idx: usize,
+        _read: &Op,
+        _src_idx: usize,
+    ) -> u32 {
+        self.dst_latency(write, dst_idx)
+    }
+
+    fn war(
+        &self,
+        _read: &Op,
+        _src_idx: usize,
+        _write: &Op,
+        _dst_idx: usize,
+    ) -> u32 {
+        // We assume the source gets read in the first 4 cycles.  We don't know
+        // how quickly the write will happen.  This is all a guess.
+        4
+    }
+
+    fn waw(&self, a: &Op, a_dst_idx: usize, _b: &Op, _b_dst_idx: usize) -> u32 {
+        // We know our latencies are wrong so assume the wrote could happen
+        // anywhere between 0 and dst_latency(a) cycles
+        self.dst_latency(a, a_dst_idx)
+    }
+
+    fn paw(&self, _write: &Op, _dst_idx: usize) -> u32 {
+        13
+    }
+}
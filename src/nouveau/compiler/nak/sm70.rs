@@ -6,6 +6,7 @@ use crate::legalize::{
     src_is_reg, src_is_upred_reg, swap_srcs_if_not_reg, LegalizeBuildHelpers,
     LegalizeBuilder,
 };
+use crate::sm70_instr_latencies::SM70InstrLatencies;
 use bitview::*;
 
 use std::collections::HashMap;
@@ -13,12 +14,18 @@ use std::ops::Range;
 
 pub struct ShaderModel70 {
     sm: u8,
+    warps_per_sm: u32,
+    latencies: SM70InstrLatencies,
 }
 
 impl ShaderModel70 {
-    pub fn new(sm: u8) -> Self {
+    pub fn new(sm: u8, warps_per_sm: u32) -> Self {
         assert!(sm >= 70);
-        Self { sm }
+        Self {
+            sm,
+            warps_per_sm,
+            latencies: SM70InstrLatencies::new(sm),
+        }
     }
 
     fn has_uniform_alu(&self) -> bool {
@@ -62,11 +69,37 @@ impl ShaderModel for ShaderModel70 {
         2
     }
 
+    fn warps_per_sm(&self) -> u32 {
+        self.warps_per_sm
+    }
+
+    fn ctas_per_sm(&self) -> u32 {
+        match self.sm {
+            75 | 86 | 87 => 16,
+            89 => 24,
+            _ => 32,
+        }
+    }
+
+    fn smem_per_sm(&self) -> u32 {
+        match self.sm {
+            75 => 64 << 10,
+            80 | 87 => 164 << 10,
+            86 | 89 => 100 << 10,
+            90 => 228 << 10,
+            _ => 96 << 10,
+        }
+    }
+
     fn crs_size(&self, max_crs_depth: u32) -> u32 {
         assert!(max_crs_depth == 0);
         0
     }
 
+    fn num_scoreboards(&self) -> u8 {
+        6
+    }
+
     fn op_can_be_uniform(&self, op: &Op) -> bool {
         if !self.has_uniform_alu() {
             return false;
@@ -105,6 +138,10 @@ impl ShaderModel for ShaderModel70 {
         }
     }
 
+    fn latencies(&self) -> &dyn InstrLatencies {
+        &self.latencies
+    }
+
     fn legalize_op(&self, b: &mut LegalizeBuilder, op: &mut Op) {
         as_sm70_op_mut(op).legalize(b);
     }
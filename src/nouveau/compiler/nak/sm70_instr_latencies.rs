@@ -0,0 +1,117 @@
+// Copyright © 2026 Collabora, Ltd.
+// SPDX-License-Identifier: MIT
+
+use crate::api::{GetDebugFlags, DEBUG};
+use crate::ir::*;
+
+use std::cmp::min;
+use std::sync::Once;
+
+/// The newest SM we have measured latencies for
+const MAX_KNOWN_SM: u8 = 89;
+
+/// Extra cycles added to every latency on SMs newer than MAX_KNOWN_SM,
+/// unless overridden with NAK_UNKNOWN_SM_MARGIN
+const UNKNOWN_SM_MARGIN: u32 = 2;
+
+static UNKNOWN_SM_WARNING: Once = Once::new();
+
+/// Instruction latencies for Volta and later
+pub struct SM70InstrLatencies {
+    sm: u8,
+    margin: u32,
+}
+
+impl SM70InstrLatencies {
+    pub fn new(sm: u8) -> Self {
+        assert!(sm >= 70);
+        if sm <= MAX_KNOWN_SM {
+            Self { sm, margin: 0 }
+        } else {
+            let margin = DEBUG.unknown_sm_margin().unwrap_or(UNKNOWN_SM_MARGIN);
+            UNKNOWN_SM_WARNING.call_once(|| {
+                eprintln!(
+                    "NAK: No latency information for SM{sm}, using SM{} \
+                     with a safety margin of {} cycles",
+                    MAX_KNOWN_SM, margin,
+                );
+            });
+            Self {
+                sm: MAX_KNOWN_SM,
+                margin,
+            }
+        }
+    }
+
+    fn add_margin(&self, latency: u32) -> u32 {
+        if latency == 0 {
+            latency
+        } else {
+            min(latency + self.margin, MAX_INSTR_DELAY.into())
+        }
+    }
+
+    fn dst_latency(&self, op: &Op, dst_idx: usize) -> u32 {
+        self.add_margin(dst_latency(op, dst_idx, self.sm < 80))
+    }
+}
+
+impl InstrLatencies for SM70InstrLatencies {
+    fn needs_scoreboard(&self, op: &Op) -> bool {
+        !op.has_fixed_latency()
+    }
+
+    fn exec(&self, op: &Op) -> u32 {
+        match op {
+            Op::Bar(_) | Op::MemBar(_) => {
+                if self.sm >= 80 {
+                    6
+                } else {
+                    5
+                }
+            }
+            Op::CCtl(_op) => {
+                // CCTL.C needs 8, CCTL.I needs 11
+                11
+            }
+            // Op::DepBar(_) => 4,
+            _ => 1, // TODO: co-issue
+        }
+    }
+
+    fn worst(&self, write: &Op, dst_idx: usize) -> u32 {
+        self.dst_latency(write, dst_idx)
+    }
+
+    fn raw(
+        &self,
+        write: &Op,
+        dst_idx: usize,
+        _read: &Op,
+        _src_idx: usize,
+    ) -> u32 {
+        self.dst_latency(write, dst_idx)
+    }
+
+    fn war(
+        &self,
+        _read: &Op,
+        _src_idx: usize,
+        _write: &Op,
+        _dst_idx: usize,
+    ) -> u32 {
+        // We assume the source gets read in the first 4 cycles.  We don't know
+        // how quickly the write will happen.  This is all a guess.
+        self.add_margin(4)
+    }
+
+    fn waw(&self, a: &Op, a_dst_idx: usize, _b: &Op, _b_dst_idx: usize) -> u32 {
+        // We know our latencies are wrong so assume the wrote could happen
+        // anywhere between 0 and dst_latency(a) cycles
+        self.dst_latency(a, a_dst_idx)
+    }
+
+    fn paw(&self, _write: &Op, _dst_idx: usize) -> u32 {
+        self.add_margin(13)
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright © 2024 Collabora, Ltd.
+// SPDX-License-Identifier: MIT
+
+use crate::ir::*;
+
+struct RegValidator<'a> {
+    sm: &'a dyn ShaderModel,
+    num_gprs: u32,
+}
+
+impl RegValidator<'_> {
+    fn validate_reg(&self, instr: &Instr, reg: &RegRef) {
+        let file = reg.file();
+        let limit = match file {
+            RegFile::GPR => self.num_gprs,
+            // Memory has no fixed size and carry is only implicitly used
+            RegFile::Carry | RegFile::Mem => return,
+            file => self.sm.num_regs(file),
+        };
+
+        if reg.idx_range().end <= limit {
+            return;
+        }
+
+        let is_zero = match file {
+            RegFile::GPR | RegFile::UGPR | RegFile::Pred | RegFile::UPred => {
+                *reg == RegRef::zero(file, 1)
+            }
+            _ => false,
+        };
+        assert!(is_zero, "Register {reg} out of range in {instr}");
+    }
+
+    fn validate_src_ref(&self, instr: &Instr, src_ref: &SrcRef) {
+        match src_ref {
+            SrcRef::SSA(_) => panic!("SSA value left after RA in {instr}"),
+            SrcRef::Reg(reg) => self.validate_reg(instr, reg),
+            SrcRef::CBuf(cb) => match &cb.buf {
+                CBuf::Binding(_) => (),
+                CBuf::BindlessSSA(_) => {
+                    panic!("SSA value left after RA in {instr}")
+                }
+                CBuf::BindlessUGPR(reg) => self.validate_reg(instr, reg),
+            },
+            SrcRef::Zero | SrcRef::True | SrcRef::False | SrcRef::Imm32(_) => {}
+        }
+    }
+
+    fn validate_instr(&self, instr: &Instr) {
+        match &instr.pred.pred_ref {
+            PredRef::None => (),
+            PredRef::SSA(_) => panic!("SSA value left after RA in {instr}"),
+            PredRef::Reg(reg) => self.validate_reg(instr, reg),
+        }
+
+        for src in instr.srcs() {
+            self.validate_src_ref(instr, &src.src_ref);
+        }
+
+        for dst in instr.dsts() {
+            match dst {
+                Dst::None => (),
+                Dst::SSA(_) => panic!("SSA value left after RA in {instr}"),
+                Dst::Reg(reg) => self.validate_reg(instr, reg),
+            }
+        }
+    }
+}
+
+impl Shader<'_> {
+    /// Checks cheap invariants which must hold on the final shader
+    ///
+    /// Unlike the debug assertions sprinkled through the passes, these are
+    /// checked in release builds too.  A failure panics which makes the
+    /// compile of this shader fail rather than handing the hardware a
+    /// shader which uses registers it never allocated.
+    pub fn validate_regs(&self) {
+        let v = RegValidator {
+            sm: self.sm,
+            num_gprs: self.info.num_gprs.into(),
+        };
+        self.for_each_instr(&mut |instr| v.validate_instr(instr));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sm70::ShaderModel70;
+
+    fn validate(num_gprs: u8, ssa_alloc: SSAValueAllocator, instr: Box<Instr>) {
+        let sm = ShaderModel70::new(80, 64);
+        let f = Function::new_linear(
+            ssa_alloc,
+            vec![vec![instr, Instr::new_boxed(OpExit {})]],
+        );
+        let s = Shader {
+            sm: &sm,
+            info: ShaderInfo {
+                max_warps_per_sm: 0,
+                num_gprs: num_gprs,
+                num_control_barriers: 0,
+                num_instrs: 0,
+                num_fma_instrs: 0,
+                num_alu_instrs: 0,
+                num_xu_instrs: 0,
+                num_mem_instrs: 0,
+                num_ctrl_instrs: 0,
+                num_static_cycles: 0,
+                num_spills_to_mem: 0,
+                num_fills_from_mem: 0,
+                num_spills_to_reg: 0,
+                num_fills_from_reg: 0,
+                slm_size: 0,
+                max_crs_depth: 0,
+                uses_global_mem: false,
+                writes_global_mem: false,
+                uses_fp64: false,
+                stage: ShaderStageInfo::Vertex,
+                io: ShaderIoInfo::None,
+            },
+            functions: vec![f],
+        };
+        s.validate_regs();
+    }
+
+    fn copy_gprs(dst: u32, src: u32) -> Box<Instr> {
+        Instr::new_boxed(OpCopy {
+            dst: RegRef::new(RegFile::GPR, dst, 1).into(),
+            src: RegRef::new(RegFile::GPR, src, 1).into(),
+        })
+    }
+
+    #[test]
+    fn test_valid_regs() {
+        validate(4, SSAValueAllocator::new(), copy_gprs(3, 0));
+
+        // The zero register is always allowed
+        let copy = Instr::new_boxed(OpCopy {
+            dst: RegRef::zero(RegFile::GPR, 1).into(),
+            src: RegRef::new(RegFile::GPR, 0, 1).into(),
+        });
+        validate(4, SSAValueAllocator::new(), copy);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_gpr_out_of_range() {
+        validate(4, SSAValueAllocator::new(), copy_gprs(4, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "SSA value left after RA")]
+    fn test_ssa_left_after_ra() {
+        let mut ssa_alloc = SSAValueAllocator::new();
+        let ssa = ssa_alloc.alloc(RegFile::GPR);
+        let copy = Instr::new_boxed(OpCopy {
+            dst: RegRef::new(RegFile::GPR, 0, 1).into(),
+            src: ssa.into(),
+        });
+        validate(4, ssa_alloc, copy);
+    }
+}
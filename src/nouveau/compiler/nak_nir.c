@@ -915,14 +915,36 @@ atomic_supported(const nir_instr *instr, const void *data)
             intr->def.bit_size == 64);
 }
 
+static bool
+relax_fp_math_instr(UNUSED nir_builder *b, nir_alu_instr *alu,
+                    UNUSED void *_data)
+{
+   if (!alu->exact && alu->fp_fast_math == 0)
+      return false;
+
+   alu->exact = false;
+   alu->fp_fast_math = 0;
+   return true;
+}
+
 void
 nak_postprocess_nir(nir_shader *nir,
                     const struct nak_compiler *nak,
                     nir_variable_mode robust2_modes,
+                    bool fast_math,
                     const struct nak_fs_key *fs_key)
 {
    UNUSED bool progress = false;
 
+   /* Dropping the preserve bits lets nir_opt_algebraic apply its inexact
+    * patterns.  The float controls execution mode is left alone so rounding
+    * and denorm handling don't change.
+    */
+   if (fast_math) {
+      OPT(nir, nir_shader_alu_pass, relax_fp_math_instr,
+          nir_metadata_control_flow, NULL);
+   }
+
    nak_optimize_nir(nir, nak);
 
    const nir_lower_subgroups_options subgroups_options = {
@@ -942,6 +964,22 @@ nak_postprocess_nir(nir_shader *nir,
    OPT(nir, nir_lower_atomics, atomic_supported);
    OPT(nir, nak_nir_lower_scan_reduce);
 
+   /* Small temporary arrays which are still indexed dynamically are
+    * cheaper as a tree of selects than as a round-trip through local
+    * memory.  Lowering the indirects lets nir_lower_vars_to_ssa turn the
+    * array into plain SSA values and peephole_select flattens the ifs.
+    *
+    * Each indirect access turns into one select per element so the cost
+    * grows linearly with the array length.  At 16 elements that's 15 SELs
+    * per load, which is still cheaper than an LDL or STL with its
+    * scoreboard wait.  The array also stays in 16 GPRs per component, so
+    * register pressure is manageable.  Intel uses the same cut-off.
+    */
+   if (nir_shader_has_local_variables(nir) &&
+       OPT(nir, nir_lower_indirect_derefs, nir_var_function_temp,
+           NAK_MAX_INDIRECT_TEMP_ARRAY_LEN))
+      nak_optimize_nir(nir, nak);
+
    if (nir_shader_has_local_variables(nir)) {
       OPT(nir, nir_lower_vars_to_explicit_types, nir_var_function_temp,
           glsl_get_natural_size_align_bytes);
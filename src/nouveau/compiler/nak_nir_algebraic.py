@@ -44,6 +44,17 @@ algebraic_lowering = [
         ('lea_nv', a, b, s), 'nak->sm >= 70'),
     (('iadd', 'a@64', ('ishl', 'b@64', '#s@32')),
         ('lea_nv', a, b, s), 'nak->sm >= 70'),
+
+    # nir_opt_algebraic always lowers 64-bit rotates but they're only a
+    # couple of SHFs on Volta+ so form them again late.
+    (('ior', ('ishl@64', a, b), ('ushr@64', a, ('iadd', 64, ('ineg', b)))),
+        ('urol', a, b), 'nak->sm >= 70'),
+    (('ior', ('ishl@64', a, b), ('ushr@64', a, ('isub', 64, b))),
+        ('urol', a, b), 'nak->sm >= 70'),
+    (('ior', ('ushr@64', a, b), ('ishl@64', a, ('iadd', 64, ('ineg', b)))),
+        ('uror', a, b), 'nak->sm >= 70'),
+    (('ior', ('ushr@64', a, b), ('ishl@64', a, ('isub', 64, b))),
+        ('uror', a, b), 'nak->sm >= 70'),
 ]
 
 def main():
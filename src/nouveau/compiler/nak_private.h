@@ -257,6 +257,11 @@ bool nak_nir_lower_non_uniform_ldcx(nir_shader *nir);
 bool nak_nir_add_barriers(nir_shader *nir, const struct nak_compiler *nak);
 bool nak_nir_lower_cf(nir_shader *nir);
 
+/* Dynamically indexed function_temp arrays with at most this many elements
+ * are lowered to selects instead of going through local memory.
+ */
+#define NAK_MAX_INDIRECT_TEMP_ARRAY_LEN 16
+
 void nak_optimize_nir(nir_shader *nir, const struct nak_compiler *nak);
 
 #ifdef __cplusplus
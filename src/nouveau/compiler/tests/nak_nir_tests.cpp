@@ -0,0 +1,75 @@
+/*
+ * Copyright © 2026 Collabora, Ltd.
+ * SPDX-License-Identifier: MIT
+ */
+
+#include "nak_private.h"
+#include "tests/nir_test.h"
+
+class nak_nir_lower_indirect_temps_test : public nir_test {
+protected:
+   nak_nir_lower_indirect_temps_test()
+      : nir_test("nak_nir_lower_indirect_temps_test")
+   {
+   }
+
+   bool lower_temp_array(unsigned len);
+   bool has_indirect_temp_deref();
+};
+
+/* Writes and reads back a temporary array of the given length with a
+ * dynamic index and then runs the same lowering as nak_preprocess_nir().
+ */
+bool
+nak_nir_lower_indirect_temps_test::lower_temp_array(unsigned len)
+{
+   nir_variable *arr =
+      nir_local_variable_create(b->impl,
+                                glsl_array_type(glsl_uint_type(), len, 0),
+                                "arr");
+
+   nir_def *idx = nir_load_local_invocation_index(b);
+   nir_deref_instr *elem =
+      nir_build_deref_array(b, nir_build_deref_var(b, arr), idx);
+   nir_store_deref(b, elem, idx, 0x1);
+   nir_def *val = nir_load_deref(b, elem);
+   nir_store_global(b, nir_imm_int64(b, 0), 4, val, 0x1);
+
+   bool progress = false;
+   NIR_PASS(progress, b->shader, nir_lower_indirect_derefs,
+            nir_var_function_temp, NAK_MAX_INDIRECT_TEMP_ARRAY_LEN);
+   NIR_PASS(_, b->shader, nir_opt_dce);
+
+   return progress;
+}
+
+bool
+nak_nir_lower_indirect_temps_test::has_indirect_temp_deref()
+{
+   nir_foreach_block(block, b->impl) {
+      nir_foreach_instr(instr, block) {
+         if (instr->type != nir_instr_type_deref)
+            continue;
+
+         nir_deref_instr *deref = nir_instr_as_deref(instr);
+         if (deref->deref_type == nir_deref_type_array &&
+             nir_deref_mode_is(deref, nir_var_function_temp) &&
+             !nir_src_is_const(deref->arr.index))
+            return true;
+      }
+   }
+
+   return false;
+}
+
+TEST_F(nak_nir_lower_indirect_temps_test, small_array_lowered)
+{
+   EXPECT_TRUE(lower_temp_array(NAK_MAX_INDIRECT_TEMP_ARRAY_LEN));
+   EXPECT_FALSE(has_indirect_temp_deref());
+}
+
+TEST_F(nak_nir_lower_indirect_temps_test, large_array_kept)
+{
+   EXPECT_FALSE(lower_temp_array(NAK_MAX_INDIRECT_TEMP_ARRAY_LEN + 1));
+   EXPECT_TRUE(has_indirect_temp_deref());
+}
@@ -24,6 +24,10 @@ enum nvk_debug {
     */
    NVK_DEBUG_VM = 1ull << 3,
 
+   /* Print NAK compiler statistics when the physical device is destroyed
+    */
+   NVK_DEBUG_NAK_STATS = 1ull << 4,
+
    /* Disable most cbufs
     *
     * Root descriptors still end up in a cbuf
@@ -90,6 +90,7 @@ nvk_init_debug_flags(struct nvk_instance *instance)
       { "push_sync", NVK_DEBUG_PUSH_SYNC },
       { "zero_memory", NVK_DEBUG_ZERO_MEMORY },
       { "vm", NVK_DEBUG_VM },
+      { "nak_stats", NVK_DEBUG_NAK_STATS },
       { "no_cbuf", NVK_DEBUG_NO_CBUF },
       { "edb_bview", NVK_DEBUG_FORCE_EDB_BVIEW },
       { "gart", NVK_DEBUG_FORCE_GART },
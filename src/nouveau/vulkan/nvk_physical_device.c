@@ -33,6 +33,7 @@
 #include "vk_shader_module.h"
 #include "vulkan/wsi/wsi_common.h"
 
+#include <inttypes.h>
 #include <sys/sysmacros.h>
 
 #include "nv_push.h"
@@ -1484,12 +1485,33 @@ fail_nvkmd:
    return result;
 }
 
+static void
+nvk_print_nak_stats(const struct nvk_physical_device *pdev)
+{
+   struct nak_compiler_stats stats;
+   nak_get_compiler_stats(pdev->nak, &stats);
+
+   fprintf(stderr, "NAK stats for %s:\n", pdev->info.device_name);
+   fprintf(stderr, "  compiles: %" PRIu64 " (%" PRIu64 " failed)\n",
+           stats.num_compiles, stats.num_failed_compiles);
+   fprintf(stderr, "  compile time: %" PRIu64 " us\n",
+           stats.compile_time_ns / 1000);
+   fprintf(stderr, "  spills to mem: %" PRIu64 "\n", stats.num_spills_to_mem);
+   fprintf(stderr, "  spills to reg: %" PRIu64 "\n", stats.num_spills_to_reg);
+   fprintf(stderr, "  cache hits: %" PRIu64 "\n", stats.num_cache_hits);
+   fprintf(stderr, "  codegen fallbacks: %" PRIu64 "\n",
+           stats.num_codegen_fallbacks);
+}
+
 void
 nvk_physical_device_destroy(struct vk_physical_device *vk_pdev)
 {
    struct nvk_physical_device *pdev =
       container_of(vk_pdev, struct nvk_physical_device, vk);
 
+   if (pdev->debug_flags & NVK_DEBUG_NAK_STATS)
+      nvk_print_nak_stats(pdev);
+
 #ifdef NVK_USE_WSI_PLATFORM
    nvk_finish_wsi(pdev);
 #endif
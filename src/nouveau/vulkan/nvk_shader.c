@@ -562,7 +562,14 @@ nvk_compile_nir_with_nak(const struct nvk_physical_device *pdev,
    if (rs->storage_buffers == VK_PIPELINE_ROBUSTNESS_BUFFER_BEHAVIOR_ROBUST_BUFFER_ACCESS_2_EXT)
       robust2_modes |= nir_var_mem_ssbo;
 
-   shader->nak = nak_compile_shader(nir, dump_asm, pdev->nak, robust2_modes, fs_key);
+   const struct nak_compile_params params = {
+      .dump_asm = dump_asm,
+      .opt_level = NAK_OPT_LEVEL_DEFAULT,
+      .robust2_modes = robust2_modes,
+      .fs_key = fs_key,
+   };
+
+   shader->nak = nak_compile_shader(nir, pdev->nak, &params);
 
    if (!shader->nak)
       return vk_errorf(pdev, VK_ERROR_UNKNOWN, "Internal compiler error in NAK");
@@ -588,6 +595,7 @@ nvk_compile_nir(struct nvk_device *dev, nir_shader *nir,
       result = nvk_compile_nir_with_nak(pdev, nir, shader_flags, rs,
                                        fs_key, shader);
    } else {
+      nak_compiler_record_codegen_fallback(pdev->nak);
       result = nvk_cg_compile_nir(pdev, nir, fs_key, shader);
    }
    if (result != VK_SUCCESS)
@@ -1168,6 +1176,8 @@ nvk_deserialize_shader(struct vk_device *vk_dev,
       }
    }
 
+   nak_compiler_record_cache_hit(nvk_device_physical(dev)->nak);
+
    *shader_out = &shader->vk;
 
    return VK_SUCCESS;
@@ -1249,6 +1259,48 @@ nvk_shader_get_executable_statistics(
       stat->value.u64 = shader->info.num_instrs;
    }
 
+   vk_outarray_append_typed(VkPipelineExecutableStatisticKHR, &out, stat) {
+      WRITE_STR(stat->name, "FMA instruction count");
+      WRITE_STR(stat->description,
+                "Number of instructions issued to the FMA pipe");
+      stat->format = VK_PIPELINE_EXECUTABLE_STATISTIC_FORMAT_UINT64_KHR;
+      stat->value.u64 = shader->info.num_fma_instrs;
+   }
+
+   vk_outarray_append_typed(VkPipelineExecutableStatisticKHR, &out, stat) {
+      WRITE_STR(stat->name, "ALU instruction count");
+      WRITE_STR(stat->description,
+                "Number of instructions issued to the integer/logic ALU pipe");
+      stat->format = VK_PIPELINE_EXECUTABLE_STATISTIC_FORMAT_UINT64_KHR;
+      stat->value.u64 = shader->info.num_alu_instrs;
+   }
+
+   vk_outarray_append_typed(VkPipelineExecutableStatisticKHR, &out, stat) {
+      WRITE_STR(stat->name, "XU instruction count");
+      WRITE_STR(stat->description,
+                "Number of multi-function, double-precision, bit-counting, "
+                "and conversion instructions");
+      stat->format = VK_PIPELINE_EXECUTABLE_STATISTIC_FORMAT_UINT64_KHR;
+      stat->value.u64 = shader->info.num_xu_instrs;
+   }
+
+   vk_outarray_append_typed(VkPipelineExecutableStatisticKHR, &out, stat) {
+      WRITE_STR(stat->name, "Memory instruction count");
+      WRITE_STR(stat->description,
+                "Number of memory, texture, and attribute instructions");
+      stat->format = VK_PIPELINE_EXECUTABLE_STATISTIC_FORMAT_UINT64_KHR;
+      stat->value.u64 = shader->info.num_mem_instrs;
+   }
+
+   vk_outarray_append_typed(VkPipelineExecutableStatisticKHR, &out, stat) {
+      WRITE_STR(stat->name, "Control instruction count");
+      WRITE_STR(stat->description,
+                "Number of control-flow, barrier, and system value "
+                "instructions, not counting NOPs");
+      stat->format = VK_PIPELINE_EXECUTABLE_STATISTIC_FORMAT_UINT64_KHR;
+      stat->value.u64 = shader->info.num_ctrl_instrs;
+   }
+
    vk_outarray_append_typed(VkPipelineExecutableStatisticKHR, &out, stat) {
       WRITE_STR(stat->name, "Static cycle count");
       WRITE_STR(stat->description,